@@ -1,7 +1,8 @@
 {
   description = "Rust development environment";
 
-  inputs = { nixpkgs.url = "github:NixOS/nixpkgs/nixos-unstable"; };
+  # rustc 1.73, the crate needing 1.70 (rust-version in Cargo.toml)
+  inputs = { nixpkgs.url = "github:NixOS/nixpkgs/nixos-23.11"; };
 
   outputs = { self, nixpkgs }:
     let
@@ -1,8 +1,23 @@
+pub mod bitboard;
 pub mod board;
+pub mod epd;
+pub mod error;
+pub mod fen;
+pub mod game;
+pub mod r#move;
+pub mod move_list;
+pub mod outcome;
+pub mod perft;
+pub mod pgn;
 pub mod piece;
 pub mod position;
-pub mod r#move;
 pub mod rules;
 pub mod rules_bb;
+pub mod san;
+pub mod square;
+pub mod uci;
 
-mod bits;
+mod magic;
+#[cfg(all(feature = "pext", target_arch = "x86_64"))]
+mod pext;
+mod zobrist;
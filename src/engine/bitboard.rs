@@ -0,0 +1,177 @@
+use std::fmt;
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not, Shl, Shr};
+
+use crate::engine::square::Square;
+
+// set of squares, bit n standing for Square::new(n)
+#[derive(PartialEq, Eq, Copy, Clone, Default, Hash)]
+pub struct Bitboard(pub u64);
+
+impl Bitboard {
+    pub const EMPTY: Bitboard = Bitboard(0);
+    pub const FULL: Bitboard = Bitboard(!0);
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub const fn popcount(self) -> u32 {
+        self.0.count_ones()
+    }
+
+    pub const fn contains(self, sq: Square) -> bool {
+        self.0 & sq.bb().0 != 0
+    }
+
+    // lowest square of a non empty set
+    pub const fn lsb(self) -> Square {
+        Square::new(self.0.trailing_zeros() as usize)
+    }
+
+    pub fn pop_lsb(&mut self) -> Square {
+        let sq = self.lsb();
+        self.0 &= self.0 - 1;
+        sq
+    }
+
+    pub fn toggle(&mut self, sq: Square) {
+        self.0 ^= sq.bb().0;
+    }
+
+    // shifts towards higher squares when offset is positive
+    pub const fn shift(self, offset: isize) -> Self {
+        if offset > 0 {
+            Bitboard(self.0 << offset)
+        } else {
+            Bitboard(self.0 >> -offset)
+        }
+    }
+}
+
+// pops squares from the lowest one
+impl Iterator for Bitboard {
+    type Item = Square;
+
+    fn next(&mut self) -> Option<Square> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(self.pop_lsb())
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.popcount() as usize;
+        (n, Some(n))
+    }
+}
+
+impl From<Square> for Bitboard {
+    fn from(sq: Square) -> Self {
+        sq.bb()
+    }
+}
+
+impl FromIterator<Square> for Bitboard {
+    fn from_iter<I: IntoIterator<Item = Square>>(iter: I) -> Self {
+        iter.into_iter()
+            .fold(Bitboard::EMPTY, |bb, sq| bb | sq.bb())
+    }
+}
+
+impl fmt::Debug for Bitboard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Bitboard({:#x})", self.0)
+    }
+}
+
+macro_rules! impl_bit_op {
+    ($op:ident, $fn:ident, $assign:ident, $assign_fn:ident) => {
+        impl $op for Bitboard {
+            type Output = Bitboard;
+
+            fn $fn(self, rhs: Bitboard) -> Bitboard {
+                Bitboard(self.0.$fn(rhs.0))
+            }
+        }
+
+        impl $assign for Bitboard {
+            fn $assign_fn(&mut self, rhs: Bitboard) {
+                self.0.$assign_fn(rhs.0)
+            }
+        }
+    };
+}
+
+impl_bit_op!(BitAnd, bitand, BitAndAssign, bitand_assign);
+impl_bit_op!(BitOr, bitor, BitOrAssign, bitor_assign);
+impl_bit_op!(BitXor, bitxor, BitXorAssign, bitxor_assign);
+
+impl Not for Bitboard {
+    type Output = Bitboard;
+
+    fn not(self) -> Bitboard {
+        Bitboard(!self.0)
+    }
+}
+
+impl Shl<u32> for Bitboard {
+    type Output = Bitboard;
+
+    fn shl(self, rhs: u32) -> Bitboard {
+        Bitboard(self.0 << rhs)
+    }
+}
+
+impl Shr<u32> for Bitboard {
+    type Output = Bitboard;
+
+    fn shr(self, rhs: u32) -> Bitboard {
+        Bitboard(self.0 >> rhs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn operators() {
+        let a = Bitboard(0b1100);
+        let b = Bitboard(0b1010);
+        assert_eq!(Bitboard(0b1000), a & b);
+        assert_eq!(Bitboard(0b1110), a | b);
+        assert_eq!(Bitboard(0b0110), a ^ b);
+        assert_eq!(Bitboard(!0b1100), !a);
+        assert_eq!(Bitboard(0b11000), a << 1);
+        assert_eq!(Bitboard(0b110), a >> 1);
+        assert_eq!(a << 8, a.shift(8));
+        assert_eq!(a >> 2, a.shift(-2));
+
+        let mut c = a;
+        c &= b;
+        c |= Bitboard(1);
+        c ^= Bitboard(0b1000);
+        assert_eq!(Bitboard(1), c);
+    }
+
+    #[test]
+    fn squares() {
+        let mut bb = Bitboard(0x8000000000000801); // a1, d2, h8
+        assert_eq!(3, bb.popcount());
+        assert!(bb.contains(Square::new(11)));
+        assert!(!bb.contains(Square::new(12)));
+        assert_eq!(Square::new(0), bb.lsb());
+
+        let squares: Vec<_> = bb.map(Square::index).collect();
+        assert_eq!(vec![0, 11, 63], squares);
+        assert_eq!(3, bb.count());
+        assert_eq!(bb, bb.collect());
+
+        assert_eq!(Square::new(0), bb.pop_lsb());
+        bb.toggle(Square::new(63));
+        assert_eq!(Bitboard(0x800), bb);
+        assert!(Bitboard::EMPTY.is_empty());
+        assert_eq!(0, Bitboard::EMPTY.count());
+    }
+}
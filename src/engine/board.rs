@@ -1,26 +1,59 @@
-use crate::engine::bits::BitOperations;
+use crate::engine::bitboard::Bitboard;
+use crate::engine::move_list::MoveList;
+use crate::engine::outcome::{DrawReason, Outcome};
 use crate::engine::piece::{Color, PieceType};
 use crate::engine::position::Position;
-use crate::engine::r#move::Move;
+use crate::engine::r#move::{Move, PackedMove};
 use crate::engine::rules::*;
+use crate::engine::rules_bb::*;
+use crate::engine::square::Square;
+use crate::engine::zobrist;
 
 use colored::Colorize;
 
-#[derive(Clone)]
-pub struct Board {
-    pub bitboards: [u64; 12],
+// what is needed to tell pseudo-legal moves apart from legal ones, computed
+// once per position rather than once per move
+struct CheckInfo {
+    king: Square,
+    checkers: Bitboard,
+    pinned: Bitboard,
+}
 
-    pub side_to_move: Color,
-    turn: u32,
+// state make_move cannot recover from the move itself
+#[derive(Copy, Clone, Debug)]
+pub struct Undo {
+    captured: Option<PieceType>,
     white_king_castling: bool,
     white_queen_castling: bool,
     black_king_castling: bool,
     black_queen_castling: bool,
-    // TODO en_passant:
-    // TODO last_fifty_turn:
+    en_passant: Option<Square>,
+    halfmove_clock: u32,
+    hash: u64,
 }
 
-const COLOR_SWITCH: usize = 6;
+#[derive(Clone, PartialEq)]
+pub struct Board {
+    // once the board is built, changed only by put_piece and remove_piece,
+    // which keep the mailbox and the hash in sync
+    pub(crate) bitboards: [Bitboard; 12],
+    // piece on each square, kept in sync with the bitboards
+    pub(crate) mailbox: [Option<(PieceType, Color)>; 64],
+
+    pub(crate) side_to_move: Color,
+    pub(crate) turn: u32,
+    pub(crate) white_king_castling: bool,
+    pub(crate) white_queen_castling: bool,
+    pub(crate) black_king_castling: bool,
+    pub(crate) black_queen_castling: bool,
+    pub(crate) en_passant: Option<Square>,
+    pub(crate) halfmove_clock: u32,
+    pub(crate) hash: u64,
+    // hashes of the positions before each move made on this board
+    pub(crate) history: Vec<u64>,
+}
+
+pub(crate) const COLOR_SWITCH: usize = 6;
 
 impl Board {
     // TODO position method
@@ -29,64 +62,326 @@ impl Board {
         // bitboards computed here:
         // https://gekomad.github.io/Cinnamon/BitboardCalculator/
         // using "little endian file and rank mapping", layout 2 (A1 bit is lsb)
-        Self {
-            bitboards: [
-                0x000000000000ff00, // white pawns
-                0x0000000000000042, // white knights
-                0x0000000000000024, // white bishops
-                0x0000000000000081, // white rooks
-                0x0000000000000010, // white queen
-                0x0000000000000008, // white king
-                0x00ff000000000000, // black pawns
-                0x4200000000000000, // black knights
-                0x2400000000000000, // black bishops
-                0x8100000000000000, // black rooks
-                0x1000000000000000, // black queen
-                0x0800000000000000, // black king
-            ],
+        let bitboards = [
+            Bitboard(0x000000000000ff00), // white pawns
+            Bitboard(0x0000000000000042), // white knights
+            Bitboard(0x0000000000000024), // white bishops
+            Bitboard(0x0000000000000081), // white rooks
+            Bitboard(0x0000000000000008), // white queen
+            Bitboard(0x0000000000000010), // white king
+            Bitboard(0x00ff000000000000), // black pawns
+            Bitboard(0x4200000000000000), // black knights
+            Bitboard(0x2400000000000000), // black bishops
+            Bitboard(0x8100000000000000), // black rooks
+            Bitboard(0x0800000000000000), // black queen
+            Bitboard(0x1000000000000000), // black king
+        ];
 
+        let mut board = Self {
+            bitboards,
+            mailbox: Self::mailbox_from(&bitboards),
             side_to_move: Color::White,
             turn: 0,
             white_king_castling: true,
             white_queen_castling: true,
             black_king_castling: true,
             black_queen_castling: true,
+            en_passant: None,
+            halfmove_clock: 0,
+            hash: 0,
+            history: Vec::new(),
+        };
+        board.hash = board.compute_hash();
+
+        board
+    }
+
+    pub(crate) fn mailbox_from(bitboards: &[Bitboard; 12]) -> [Option<(PieceType, Color)>; 64] {
+        let mut mailbox = [None; 64];
+        for (i, bb) in bitboards.iter().enumerate() {
+            for sq in *bb {
+                mailbox[sq.index()] = Some((
+                    PieceType::ALL[i % COLOR_SWITCH],
+                    Color::ALL[i / COLOR_SWITCH],
+                ));
+            }
         }
+
+        mailbox
     }
 
-    fn is_move_legal(&self, _m: &Move) -> bool {
-        true
+    fn put_piece(&mut self, sq: Square, piece: PieceType, color: Color) {
+        self.bitboards[color as usize * COLOR_SWITCH + piece as usize] |= sq.bb();
+        self.mailbox[sq.index()] = Some((piece, color));
+        self.hash ^= zobrist::piece_key(piece, color, sq);
     }
 
-    pub fn generate_legal_moves(&self) -> Vec<Move> {
-        let mut v = Vec::new();
+    fn remove_piece(&mut self, sq: Square) -> Option<(PieceType, Color)> {
+        let removed = self.mailbox[sq.index()].take();
+        if let Some((piece, color)) = removed {
+            self.bitboards[color as usize * COLOR_SWITCH + piece as usize] &= !sq.bb();
+            self.hash ^= zobrist::piece_key(piece, color, sq);
+        }
 
-        let generators: Vec<fn(&Board) -> Vec<Move>> = vec![
-            generate_pawn_moves,
-            generate_rook_moves,
-            generate_knight_moves,
-            generate_bishop_moves,
-            generate_queen_moves,
-            generate_king_moves,
-        ];
+        removed
+    }
+
+    fn move_piece(&mut self, from: Square, to: Square) {
+        if let Some((piece, color)) = self.remove_piece(from) {
+            self.put_piece(to, piece, color);
+        }
+    }
+
+    pub fn fullmove_number(&self) -> u32 {
+        // `turn` counts the moves played by white
+        match self.side_to_move {
+            Color::White => self.turn + 1,
+            Color::Black => self.turn,
+        }
+    }
+
+    pub fn halfmove_clock(&self) -> u32 {
+        self.halfmove_clock
+    }
+
+    pub fn side_to_move(&self) -> Color {
+        self.side_to_move
+    }
+
+    // indexed by `color as usize * 6 + piece as usize`
+    pub fn bitboards(&self) -> &[Bitboard; 12] {
+        &self.bitboards
+    }
+
+    fn check_info(&self) -> CheckInfo {
+        let us = self.side_to_move;
+        let king = self.king_square(us);
+        let occupancy = self.occupancy_bb();
+        let checkers = self.checkers();
+
+        let diagonal = self.pieces(PieceType::Bishop, !us) | self.pieces(PieceType::Queen, !us);
+        let orthogonal = self.pieces(PieceType::Rook, !us) | self.pieces(PieceType::Queen, !us);
+        let snipers = (bishop_attacks_bb(king, Bitboard::EMPTY) & diagonal)
+            | (rook_attacks_bb(king, Bitboard::EMPTY) & orthogonal);
+
+        let mut pinned = Bitboard::EMPTY;
+        for sniper in snipers {
+            let blockers = between_bb(king, sniper) & occupancy;
+            if blockers.popcount() == 1 {
+                pinned |= blockers & self.color_bb(us);
+            }
+        }
+
+        CheckInfo {
+            king,
+            checkers,
+            pinned,
+        }
+    }
+
+    // whether m is one of the legal moves of the position, made by the piece
+    // it names
+    pub fn is_move_legal(&self, m: &Move) -> bool {
+        let packed = self.pack_move(m);
+        self[packed.from_square()].map(|(piece, _)| piece) == Some(m.piece_type)
+            && packed.promotion() == m.promotion
+            && self.generate_legal_moves().contains(&packed)
+    }
+
+    fn is_move_legal_with(&self, m: PackedMove, info: &CheckInfo) -> bool {
+        let (from, to) = (m.from_square(), m.to_square());
+
+        if from == info.king {
+            // the king must not hide behind itself from a slider
+            let occupancy = self.occupancy_bb() ^ from.bb();
+            return (self.attackers_to(to, occupancy) & self.enemies_bb()).is_empty();
+        }
+
+        if m.is_en_passant() {
+            // two pawns leave the capturing rank at once, which no pin
+            // detection can see: replay the capture and look for attackers
+            let captured = Square::from_coords(from.rank(), to.file());
+            let occupancy = (self.occupancy_bb() ^ from.bb() ^ captured.bb()) | to.bb();
+            let attackers = self.attackers_to(info.king, occupancy) & self.enemies_bb();
+            return (attackers & !captured.bb()).is_empty();
+        }
+
+        match info.checkers.popcount() {
+            0 => {}
+            1 => {
+                // capture the checker or block the check
+                let evasions = info.checkers | between_bb(info.king, info.checkers.lsb());
+                if !evasions.contains(to) {
+                    return false;
+                }
+            }
+            _ => return false, // only the king can escape a double check
+        }
+
+        // a pinned piece can only move along the line it is pinned on
+        !info.pinned.contains(from) || line_bb(info.king, from).contains(to)
+    }
+
+    pub fn generate_legal_moves(&self) -> MoveList {
+        let mut moves = MoveList::new();
+        self.generate_legal_moves_into(&mut moves);
+        moves
+    }
+
+    // clears `moves` and fills it with the legal moves of the position
+    pub fn generate_legal_moves_into(&self, moves: &mut MoveList) {
+        moves.clear();
+        generate_pawn_moves(self, moves);
+        generate_rook_moves(self, moves);
+        generate_knight_moves(self, moves);
+        generate_bishop_moves(self, moves);
+        generate_queen_moves(self, moves);
+        generate_king_moves(self, moves);
+
+        let info = self.check_info();
+        moves.retain(|m| self.is_move_legal_with(*m, &info));
+    }
+
+    // the packed move for m in this position, which tells captures, castling,
+    // en passant and double pushes apart
+    pub fn pack_move(&self, m: &Move) -> PackedMove {
+        let from = Square::from(m.start);
+        let to = Square::from(m.end);
+        let distance = to.index() as isize - from.index() as isize;
+
+        let flags = match m.piece_type {
+            PieceType::Pawn
+                if Some(to) == self.en_passant && self.is_en_passant_capture(from, to) =>
+            {
+                PackedMove::EN_PASSANT
+            }
+            PieceType::Pawn if distance.abs() == 16 => PackedMove::DOUBLE_PUSH,
+            PieceType::King if distance == 2 => PackedMove::KING_CASTLE,
+            PieceType::King if distance == -2 => PackedMove::QUEEN_CASTLE,
+            _ => {
+                let capture = if self.enemies_bb().contains(to) {
+                    PackedMove::CAPTURE
+                } else {
+                    PackedMove::QUIET
+                };
+                // promoting to a pawn or a king packs as no promotion
+                capture
+                    | m.promotion
+                        .and_then(PackedMove::promotion_flags)
+                        .unwrap_or(0)
+            }
+        };
+
+        PackedMove::new(from, to, flags)
+    }
+
+    // the rich move for m in this position, None if its start square is empty
+    pub fn unpack_move(&self, m: PackedMove) -> Option<Move> {
+        let (piece_type, _) = self.mailbox[m.from_square().index()]?;
+
+        Some(Move {
+            start: m.from_square().into(),
+            end: m.to_square().into(),
+            piece_type,
+
+            is_capture: m.is_capture(),
+            promotion: m.promotion(),
+        })
+    }
+
+    // whether an enemy pawn stands next to `from`, in front of the en passant
+    // target `to`
+    fn is_en_passant_capture(&self, from: Square, to: Square) -> bool {
+        let enemy_pawns = self.pieces(PieceType::Pawn, !self.side_to_move);
+        enemy_pawns.contains(Square::from_coords(from.rank(), to.file()))
+    }
 
-        for generator in generators {
-            v.append(
-                &mut (generator)(&self)
-                    .into_iter()
-                    .filter(|m| self.is_move_legal(m))
-                    .collect(),
-            );
+    // square of the piece taken by m, if any
+    fn captured_square(m: PackedMove) -> Option<Square> {
+        if m.is_en_passant() {
+            // the captured pawn stands next to the capturing one
+            Some(Square::from_coords(
+                m.from_square().rank(),
+                m.to_square().file(),
+            ))
+        } else if m.is_capture() {
+            Some(m.to_square())
+        } else {
+            None
         }
+    }
 
-        v
+    pub fn is_capture(&self, m: &Move) -> bool {
+        self.pack_move(m).is_capture()
     }
 
     pub fn do_move(&mut self, m: &Move) {
-        let mut bb = self[m.piece_type];
-        bb.toggle_bit(m.start.rank as usize * 8 + m.start.file as usize);
-        bb.toggle_bit(m.end.rank as usize * 8 + m.end.file as usize);
-        self[m.piece_type] = bb;
+        self.make_move(self.pack_move(m));
+    }
+
+    // rook squares (from, to) if m is a castling move
+    pub(crate) fn castling_rook_squares(m: PackedMove) -> Option<(Square, Square)> {
+        let to = m.to_square();
+        match m.flags() {
+            PackedMove::KING_CASTLE => Some((to.offset(1), to.offset(-1))),
+            PackedMove::QUEEN_CASTLE => Some((to.offset(-2), to.offset(1))),
+            _ => None,
+        }
+    }
+
+    pub fn make_move(&mut self, m: PackedMove) -> Undo {
+        let (from, to) = (m.from_square(), m.to_square());
+        let moved = self.mailbox[from.index()].map(|(piece, _)| piece);
+
+        let mut undo = Undo {
+            captured: None,
+            white_king_castling: self.white_king_castling,
+            white_queen_castling: self.white_queen_castling,
+            black_king_castling: self.black_king_castling,
+            black_queen_castling: self.black_queen_castling,
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+            hash: self.hash,
+        };
+
+        self.history.push(self.hash);
+
+        // both depend on the whole position, take them out before it changes
+        self.hash ^= zobrist::castling_key(self) ^ zobrist::en_passant_key(self);
+
+        // only an enemy piece can be taken, whatever the flags of m say
+        let captured = Self::captured_square(m).filter(|sq| self.enemies_bb().contains(*sq));
+        if let Some(captured) = captured {
+            undo.captured = self.remove_piece(captured).map(|(piece, _)| piece);
+        }
+
+        // captures and pawn moves are irreversible
+        if undo.captured.is_some() || moved == Some(PieceType::Pawn) {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+
+        self.move_piece(from, to);
+
+        if let Some(promotion) = m.promotion() {
+            self.remove_piece(to);
+            self.put_piece(to, promotion, self.side_to_move);
+        }
+
+        if let Some((rook_from, rook_to)) = Self::castling_rook_squares(m) {
+            self.move_piece(rook_from, rook_to);
+        }
+
+        // moving from or to a corner either moves or captures its rook
+        self.revoke_castling_rights(from);
+        self.revoke_castling_rights(to);
+
+        self.en_passant = if m.is_double_push() {
+            Some(Square::new((from.index() + to.index()) / 2))
+        } else {
+            None
+        };
 
         self.side_to_move = if self.side_to_move == Color::White {
             self.turn += 1;
@@ -94,78 +389,212 @@ impl Board {
         } else {
             Color::White
         };
+
+        self.hash ^= zobrist::castling_key(self)
+            ^ zobrist::en_passant_key(self)
+            ^ zobrist::side_key(Color::White);
+
+        undo
     }
 
-    pub fn enemies_bb(&self) -> u64 {
-        let offset = !self.side_to_move as usize * COLOR_SWITCH;
+    // m must be the last move made, undo the record make_move returned for it
+    pub fn unmake_move(&mut self, m: PackedMove, undo: Undo) {
+        let (from, to) = (m.from_square(), m.to_square());
 
-        self.bitboards[offset..(COLOR_SWITCH + offset)]
-            .iter()
-            .fold(0u64, |res, bb| res | bb)
+        self.side_to_move = if self.side_to_move == Color::White {
+            Color::Black
+        } else {
+            self.turn -= 1;
+            Color::White
+        };
+
+        self.white_king_castling = undo.white_king_castling;
+        self.white_queen_castling = undo.white_queen_castling;
+        self.black_king_castling = undo.black_king_castling;
+        self.black_queen_castling = undo.black_queen_castling;
+        self.en_passant = undo.en_passant;
+        self.halfmove_clock = undo.halfmove_clock;
+
+        if let Some((rook_from, rook_to)) = Self::castling_rook_squares(m) {
+            self.move_piece(rook_to, rook_from);
+        }
+
+        if m.promotion().is_some() {
+            self.remove_piece(to);
+            self.put_piece(to, PieceType::Pawn, self.side_to_move);
+        }
+
+        self.move_piece(to, from);
+
+        if let (Some(captured), Some(sq)) = (undo.captured, Self::captured_square(m)) {
+            self.put_piece(sq, captured, !self.side_to_move);
+        }
+
+        self.hash = undo.hash;
+        self.history.pop();
     }
 
-    pub fn friends_bb(&self) -> u64 {
-        let offset = self.side_to_move as usize * COLOR_SWITCH;
+    fn revoke_castling_rights(&mut self, sq: Square) {
+        match sq.index() {
+            0 => self.white_queen_castling = false,
+            4 => {
+                self.white_king_castling = false;
+                self.white_queen_castling = false;
+            }
+            7 => self.white_king_castling = false,
+            56 => self.black_queen_castling = false,
+            60 => {
+                self.black_king_castling = false;
+                self.black_queen_castling = false;
+            }
+            63 => self.black_king_castling = false,
+            _ => {}
+        }
+    }
+
+    pub fn pieces(&self, piece: PieceType, color: Color) -> Bitboard {
+        self.bitboards[color as usize * COLOR_SWITCH + piece as usize]
+    }
+
+    pub fn color_bb(&self, color: Color) -> Bitboard {
+        let offset = color as usize * COLOR_SWITCH;
 
         self.bitboards[offset..(COLOR_SWITCH + offset)]
             .iter()
-            .fold(0u64, |res, bb| res | bb)
+            .fold(Bitboard::EMPTY, |res, bb| res | *bb)
+    }
+
+    pub fn occupancy_bb(&self) -> Bitboard {
+        self.bitboards
+            .iter()
+            .fold(Bitboard::EMPTY, |res, bb| res | *bb)
+    }
+
+    pub fn king_square(&self, color: Color) -> Square {
+        self.pieces(PieceType::King, color).lsb()
+    }
+
+    // pieces of both colors attacking sq, sliders being blocked by occupancy
+    pub fn attackers_to(&self, sq: Square, occupancy: Bitboard) -> Bitboard {
+        let both = |piece| self.pieces(piece, Color::White) | self.pieces(piece, Color::Black);
+        let diagonal = both(PieceType::Bishop) | both(PieceType::Queen);
+        let orthogonal = both(PieceType::Rook) | both(PieceType::Queen);
+
+        // a pawn attacks sq if a pawn of the other color on sq would attack it
+        (pawn_attacks_bb(sq, Color::Black) & self.pieces(PieceType::Pawn, Color::White))
+            | (pawn_attacks_bb(sq, Color::White) & self.pieces(PieceType::Pawn, Color::Black))
+            | (knight_attacks_bb(sq) & both(PieceType::Knight))
+            | (king_attacks_bb(sq) & both(PieceType::King))
+            | (bishop_attacks_bb(sq, occupancy) & diagonal)
+            | (rook_attacks_bb(sq, occupancy) & orthogonal)
+    }
+
+    pub fn enemies_bb(&self) -> Bitboard {
+        self.color_bb(!self.side_to_move)
+    }
+
+    pub fn friends_bb(&self) -> Bitboard {
+        self.color_bb(self.side_to_move)
+    }
+
+    // enemy pieces giving check to the side to move
+    pub fn checkers(&self) -> Bitboard {
+        let us = self.side_to_move;
+        self.attackers_to(self.king_square(us), self.occupancy_bb()) & self.color_bb(!us)
+    }
+
+    pub fn is_check(&self) -> bool {
+        !self.checkers().is_empty()
+    }
+
+    pub fn is_checkmate(&self) -> bool {
+        self.is_check() && self.generate_legal_moves().is_empty()
+    }
+
+    pub fn is_stalemate(&self) -> bool {
+        !self.is_check() && self.generate_legal_moves().is_empty()
     }
 
-    // TODO
-    // fen constructor
-    // is_check
-    // is_checkmate
-    // is_draw
+    // None while the game goes on, draws that must be claimed are left to
+    // can_claim_draw
+    pub fn outcome(&self) -> Option<Outcome> {
+        if self.generate_legal_moves().is_empty() {
+            return if self.is_check() {
+                Some(Outcome::Win(!self.side_to_move))
+            } else {
+                Some(Outcome::Draw(DrawReason::Stalemate))
+            };
+        }
+
+        if self.is_insufficient_material() {
+            Some(Outcome::Draw(DrawReason::InsufficientMaterial))
+        } else if self.is_fivefold_repetition() {
+            Some(Outcome::Draw(DrawReason::FivefoldRepetition))
+        } else if self.is_seventy_five_moves() {
+            Some(Outcome::Draw(DrawReason::SeventyFiveMoves))
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl std::ops::Index<Position> for Board {
-    type Output = (PieceType, Color);
+    type Output = Option<(PieceType, Color)>;
 
     // operator[]: piece type and color from position
-    fn index(&self, _pos: Position) -> &Self::Output {
-        todo!();
+    fn index(&self, pos: Position) -> &Self::Output {
+        &self[Square::from(pos)]
+    }
+}
+
+impl std::ops::Index<Square> for Board {
+    type Output = Option<(PieceType, Color)>;
+
+    fn index(&self, sq: Square) -> &Self::Output {
+        &self.mailbox[sq.index()]
     }
 }
 
 impl std::ops::Index<PieceType> for Board {
-    type Output = u64;
+    type Output = Bitboard;
 
     fn index(&self, piece: PieceType) -> &Self::Output {
         &self.bitboards[self.side_to_move as usize * COLOR_SWITCH + piece as usize]
     }
 }
 
+// tests set up positions piece by piece, leaving the mailbox and the hash
+// behind
+#[cfg(test)]
 impl std::ops::IndexMut<PieceType> for Board {
-    fn index_mut(&mut self, piece: PieceType) -> &mut u64 {
+    fn index_mut(&mut self, piece: PieceType) -> &mut Bitboard {
         &mut self.bitboards[self.side_to_move as usize * COLOR_SWITCH + piece as usize]
     }
 }
 
 impl std::fmt::Debug for Board {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut repr = [(); 8 * 8].map(|_| ".".normal());
-
-        for (piece_index, board) in self.bitboards.iter().enumerate() {
-            for j in (0..8).rev() {
-                for k in (0..8).rev() {
-                    let bit_index = j * 8 + k;
-                    if board & (1 << bit_index) != 0 {
-                        let c: char = PieceType::from(piece_index % COLOR_SWITCH).into();
-                        let s: String = c.into();
-                        repr[bit_index] = if piece_index < COLOR_SWITCH {
-                            s.cyan()
-                        } else {
-                            s.purple()
-                        };
-                    }
+        let repr = self.mailbox.map(|piece| match piece {
+            Some((piece, color)) => {
+                let c: char = piece.into();
+                let s: String = c.into();
+                match color {
+                    Color::White => s.cyan(),
+                    Color::Black => s.purple(),
                 }
             }
-        }
+            None => ".".normal(),
+        });
 
         for i in (0..8).rev() {
             write!(f, "{}  ", i + 1)?;
-            for j in (0..8).rev() {
+            for j in 0..8 {
                 write!(f, "{} ", repr[i * 8 + j])?;
             }
             writeln!(f)?;
@@ -178,33 +607,56 @@ impl std::fmt::Debug for Board {
 pub mod tests {
     use super::*;
     use crate::engine::position::{File, Rank};
-    use crate::engine::rules::tests::moves_to_u64;
+    use crate::engine::rules::tests::{generate, moves_to_u64};
 
     impl Board {
         pub fn empty() -> Self {
             Self {
-                bitboards: [0; 12],
+                bitboards: [Bitboard::EMPTY; 12],
+                mailbox: [None; 64],
                 side_to_move: Color::White,
                 turn: 0,
-                white_king_castling: true,
-                white_queen_castling: true,
-                black_king_castling: true,
-                black_queen_castling: true,
+                white_king_castling: false,
+                white_queen_castling: false,
+                black_king_castling: false,
+                black_queen_castling: false,
+                en_passant: None,
+                halfmove_clock: 0,
+                hash: 0,
+                history: Vec::new(),
             }
         }
 
-        fn at(&self, piece: PieceType, color: Color) -> u64 {
-            self.bitboards[color as usize * COLOR_SWITCH + piece as usize]
+        pub fn set_bb(&mut self, piece: PieceType, color: Color, bb: Bitboard) {
+            self.bitboards[color as usize * COLOR_SWITCH + piece as usize] = bb;
+            self.mailbox = Self::mailbox_from(&self.bitboards);
         }
+    }
 
-        pub fn set_bb(&mut self, piece: PieceType, color: Color, bb: u64) {
-            self.bitboards[color as usize * COLOR_SWITCH + piece as usize] = bb;
+    // xorshift64, enough to pick random moves reproducibly
+    pub struct Rng(pub u64);
+
+    impl Rng {
+        pub fn next_u64(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
         }
     }
 
+    pub const TEST_FENS: [&str; 6] = [
+        crate::engine::fen::STARTING_FEN,
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+    ];
+
     pub fn print_u64(b: u64) {
         for i in (0..8).rev() {
-            for j in (0..8).rev() {
+            for j in 0..8 {
                 let index = i * 8 + j;
                 print!("{} ", if b & (1 << index) != 0 { '1' } else { '.' });
             }
@@ -228,38 +680,357 @@ pub mod tests {
         );
 
         assert!(board.is_move_legal(&m));
+        let king_leap = Move::new(
+            "e1".parse().unwrap(),
+            "e5".parse().unwrap(),
+            PieceType::King,
+        );
+        assert!(!board.is_move_legal(&king_leap));
+        let wrong_piece = Move::new(m.start, m.end, PieceType::Queen);
+        assert!(!board.is_move_legal(&wrong_piece));
 
         board.do_move(&m);
 
         assert_eq!(board.turn, 1);
         assert_eq!(board.side_to_move, Color::Black);
-        assert_eq!(0x1000ef00, board.at(PieceType::Pawn, Color::White));
+        assert_eq!(
+            Bitboard(0x1000ef00),
+            board.pieces(PieceType::Pawn, Color::White)
+        );
+        assert_eq!(Some("e3".parse().unwrap()), board.en_passant);
+    }
+
+    fn play(board: &mut Board, from: usize, to: usize, piece_type: PieceType) {
+        board.do_move(&Move::new(
+            Square::new(from).into(),
+            Square::new(to).into(),
+            piece_type,
+        ));
+    }
+
+    fn fen_without_clocks(board: &Board) -> String {
+        let fen = board.to_fen();
+        fen.rsplitn(3, ' ').last().unwrap().to_string()
+    }
+
+    #[test]
+    fn do_move_castling() {
+        let mut board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        play(&mut board, 4, 6, PieceType::King);
+        assert_eq!("r3k2r/8/8/8/8/8/8/R4RK1 b kq -", fen_without_clocks(&board));
+
+        play(&mut board, 60, 58, PieceType::King);
+        assert_eq!("2kr3r/8/8/8/8/8/8/R4RK1 w - -", fen_without_clocks(&board));
+    }
+
+    #[test]
+    fn do_move_revoke_castling_rights() {
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+
+        let mut board = Board::from_fen(fen).unwrap();
+        play(&mut board, 4, 12, PieceType::King);
+        assert_eq!("r3k2r/8/8/8/8/8/4K3/R6R b kq -", fen_without_clocks(&board));
+
+        let mut board = Board::from_fen(fen).unwrap();
+        play(&mut board, 7, 15, PieceType::Rook);
+        assert_eq!(
+            "r3k2r/8/8/8/8/8/7R/R3K3 b Qkq -",
+            fen_without_clocks(&board)
+        );
+
+        // capturing a rook on its home square
+        let mut board = Board::from_fen(fen).unwrap();
+        play(&mut board, 0, 56, PieceType::Rook);
+        assert_eq!("R3k2r/8/8/8/8/8/8/4K2R b Kk -", fen_without_clocks(&board));
+    }
+
+    #[test]
+    fn do_move_captures() {
+        let mut board = Board::from_fen("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1").unwrap();
+        play(&mut board, 28, 35, PieceType::Pawn);
+        assert_eq!("4k3/8/8/3P4/8/8/8/4K3 b - -", fen_without_clocks(&board));
+        assert!((board.color_bb(Color::Black) & board.color_bb(Color::White)).is_empty());
+
+        let mut board = Board::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+        play(&mut board, 36, 43, PieceType::Pawn);
+        assert_eq!("4k3/8/3P4/8/8/8/8/4K3 b - -", fen_without_clocks(&board));
+    }
+
+    #[test]
+    fn do_move_promotions() {
+        let fen = "3r2k1/4P3/8/8/8/8/8/4K3 w - - 0 1";
+
+        let mut board = Board::from_fen(fen).unwrap();
+        board.do_move(
+            &Move::new(
+                Square::new(52).into(),
+                Square::new(59).into(),
+                PieceType::Pawn,
+            )
+            .with_promotion(PieceType::Knight),
+        );
+        assert_eq!("3N2k1/8/8/8/8/8/8/4K3 b - -", fen_without_clocks(&board));
+
+        let mut board = Board::from_fen(fen).unwrap();
+        board.do_move(
+            &Move::new(
+                Square::new(52).into(),
+                Square::new(60).into(),
+                PieceType::Pawn,
+            )
+            .with_promotion(PieceType::Queen),
+        );
+        assert_eq!("3rQ1k1/8/8/8/8/8/8/4K3 b - -", fen_without_clocks(&board));
+    }
+
+    #[test]
+    fn en_passant_without_enemy_pawn() {
+        let mut board = Board::from_fen("4k3/8/8/8/8/8/3PP3/4K3 w - - 0 1").unwrap();
+        board.en_passant = Some("e3".parse().unwrap());
+        let before = board.clone();
+
+        let m = Move::new(
+            "d2".parse().unwrap(),
+            "e3".parse().unwrap(),
+            PieceType::Pawn,
+        );
+        assert!(!board.pack_move(&m).is_en_passant());
+
+        // a forged flag must not take our own pawn
+        let forged = PackedMove::new(m.start.into(), m.end.into(), PackedMove::EN_PASSANT);
+        let undo = board.make_move(forged);
+        assert_eq!(
+            Some((PieceType::Pawn, Color::White)),
+            board["e2".parse::<Square>().unwrap()]
+        );
+        board.unmake_move(forged, undo);
+        assert_eq!(before.bitboards, board.bitboards);
+        assert_eq!("4k3/8/8/8/8/8/3PP3/4K3 w - e3 0 1", board.to_fen());
+    }
+
+    #[test]
+    fn invalid_promotion() {
+        let mut board = Board::from_fen("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let m = Move::new(
+            "b7".parse().unwrap(),
+            "b8".parse().unwrap(),
+            PieceType::Pawn,
+        );
+
+        for piece in [PieceType::King, PieceType::Pawn] {
+            let m = m.with_promotion(piece);
+            assert_eq!(None, board.pack_move(&m).promotion());
+            assert!(!board.is_move_legal(&m));
+            assert!(!board.is_capture(&m));
+            assert_eq!("b8", board.to_san(&m));
+        }
+
+        board.do_move(&m.with_promotion(PieceType::King));
+        assert_eq!(
+            Some((PieceType::Pawn, Color::White)),
+            board["b8".parse::<Square>().unwrap()]
+        );
+    }
+
+    #[test]
+    fn packed_moves_round_trip() {
+        let fens = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        ];
+
+        for fen in fens {
+            let board = Board::from_fen(fen).unwrap();
+            for m in board.generate_legal_moves() {
+                let rich = board.unpack_move(m).unwrap();
+                assert_eq!(m, board.pack_move(&rich), "{:?}", rich);
+                assert_eq!(m.is_capture(), board.is_capture(&rich), "{:?}", rich);
+                assert_eq!(m.is_capture(), rich.is_capture, "{:?}", rich);
+                assert_eq!(m.promotion(), rich.promotion, "{:?}", rich);
+            }
+        }
+    }
+
+    #[test]
+    fn make_unmake_random_games() {
+        let mut rng = Rng(0x9e3779b97f4a7c15);
+
+        for fen in TEST_FENS {
+            for _ in 0..20 {
+                let mut board = Board::from_fen(fen).unwrap();
+                let original = board.clone();
+                let mut played = Vec::new();
+
+                for _ in 0..80 {
+                    let moves = board.generate_legal_moves();
+                    if moves.is_empty() {
+                        break;
+                    }
+                    let m = moves[rng.next_u64() as usize % moves.len()];
+
+                    let before = board.clone();
+                    let undo = board.make_move(m);
+
+                    let mut after = board.clone();
+                    after.unmake_move(m, undo);
+                    assert!(before == after, "{} {:?}", before.to_fen(), m);
+
+                    played.push((m, undo));
+                }
+
+                while let Some((m, undo)) = played.pop() {
+                    board.unmake_move(m, undo);
+                }
+                assert!(original == board, "{}", fen);
+            }
+        }
+    }
+
+    #[test]
+    fn index_position() {
+        let board = Board::new();
+        let at = |rank, file| board[Position { rank, file }];
+
+        assert_eq!(
+            Some((PieceType::Queen, Color::White)),
+            at(Rank::One, File::D)
+        );
+        assert_eq!(
+            Some((PieceType::King, Color::Black)),
+            at(Rank::Eight, File::E)
+        );
+        assert_eq!(
+            Some((PieceType::Knight, Color::Black)),
+            at(Rank::Eight, File::G)
+        );
+        assert_eq!(
+            Some((PieceType::Pawn, Color::White)),
+            at(Rank::Two, File::A)
+        );
+        assert_eq!(None, at(Rank::Four, File::E));
+    }
+
+    #[test]
+    fn mailbox_follows_moves() {
+        let mut rng = Rng(0x2545f4914f6cdd1d);
+
+        for fen in TEST_FENS {
+            let mut board = Board::from_fen(fen).unwrap();
+            for _ in 0..100 {
+                let moves = board.generate_legal_moves();
+                if moves.is_empty() {
+                    break;
+                }
+                board.make_move(moves[rng.next_u64() as usize % moves.len()]);
+                assert!(Board::mailbox_from(&board.bitboards) == board.mailbox);
+            }
+        }
+    }
+
+    #[test]
+    fn check_and_checkers() {
+        let board = Board::new();
+        assert!(!board.is_check());
+        assert_eq!(Bitboard(0), board.checkers());
+
+        // knight on f3 and rook on e8
+        let board = Board::from_fen("4r1k1/8/8/8/8/5n2/8/4K3 w - - 0 1").unwrap();
+        assert!(board.is_check());
+        assert_eq!(Bitboard(0x1000000000200000), board.checkers());
+        assert!(!board.is_checkmate());
+    }
+
+    #[test]
+    fn checkmate() {
+        // fool's mate
+        let board =
+            Board::from_fen("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3")
+                .unwrap();
+        assert!(board.is_checkmate());
+        assert!(!board.is_stalemate());
+        assert_eq!(Some(Outcome::Win(Color::Black)), board.outcome());
+
+        // back rank mate
+        let board = Board::from_fen("3R2k1/5ppp/8/8/8/8/8/6K1 b - - 0 1").unwrap();
+        assert!(board.is_checkmate());
+        assert_eq!(Some(Outcome::Win(Color::White)), board.outcome());
+    }
+
+    #[test]
+    fn stalemate() {
+        let board = Board::from_fen("7k/5Q2/8/8/8/8/8/6K1 b - - 0 1").unwrap();
+        assert!(board.is_stalemate());
+        assert!(!board.is_checkmate());
+        assert_eq!(Some(Outcome::Draw(DrawReason::Stalemate)), board.outcome());
+
+        assert_eq!(None, Board::new().outcome());
     }
 
     #[test]
     fn test_blockers_bb() {
         let board = Board::new();
-        assert_eq!(0xffff000000000000, board.enemies_bb());
-        assert_eq!(0xffff, board.friends_bb());
+        assert_eq!(Bitboard(0xffff000000000000), board.enemies_bb());
+        assert_eq!(Bitboard(0xffff), board.friends_bb());
     }
 
     #[test]
     fn legal_moves_pieces_start() {
         let board = Board::new();
 
-        let moves = generate_rook_moves(&board);
+        let moves = generate(generate_rook_moves, &board);
         assert_eq!(0, moves.len(), "expected 0, got {}", moves_to_u64(&moves));
 
-        let moves = generate_knight_moves(&board);
+        let moves = generate(generate_knight_moves, &board);
         assert_eq!(4, moves.len(), "expected 4, got {}", moves_to_u64(&moves));
 
-        let moves = generate_bishop_moves(&board);
+        let moves = generate(generate_bishop_moves, &board);
         assert_eq!(0, moves.len(), "expected 0, got {}", moves_to_u64(&moves));
 
-        let moves = generate_queen_moves(&board);
+        let moves = generate(generate_queen_moves, &board);
         assert_eq!(0, moves.len(), "expected 0, got {}", moves_to_u64(&moves));
 
-        let moves = generate_king_moves(&board);
+        let moves = generate(generate_king_moves, &board);
         assert_eq!(0, moves.len(), "expected 0, got {}", moves_to_u64(&moves));
     }
+
+    fn legal_moves_count(fen: &str) -> usize {
+        Board::from_fen(fen).unwrap().generate_legal_moves().len()
+    }
+
+    #[test]
+    fn legal_moves_pinned() {
+        // the rook can only move along the e file
+        assert_eq!(9, legal_moves_count("4k3/4r3/8/8/8/8/4R3/4K3 w - - 0 1"));
+        // the bishop cannot move at all
+        assert_eq!(4, legal_moves_count("4k3/4r3/8/8/8/8/4B3/4K3 w - - 0 1"));
+        // the knight cannot move at all
+        assert_eq!(4, legal_moves_count("4k3/8/8/b7/8/8/3N4/4K3 w - - 0 1"));
+    }
+
+    #[test]
+    fn legal_moves_check_evasion() {
+        // the king cannot step along the checking ray
+        assert_eq!(3, legal_moves_count("4k3/8/8/8/8/8/8/R3K2r w - - 0 1"));
+        // the rook can block on g1
+        assert_eq!(4, legal_moves_count("4k3/8/8/8/8/8/6R1/4K2r w - - 0 1"));
+        // the knight checker can be captured by the queen
+        assert_eq!(4, legal_moves_count("4k3/8/8/8/8/3n4/8/3QK3 w - - 0 1"));
+        // double check: only king moves
+        assert_eq!(2, legal_moves_count("4k3/8/8/8/8/5n2/8/R3K2r w - - 0 1"));
+    }
+
+    #[test]
+    fn legal_moves_king_attacked_squares() {
+        // the pawn and the enemy king cover every square but f1
+        assert_eq!(1, legal_moves_count("8/8/8/8/8/4k3/2p5/4K3 w - - 0 1"));
+    }
+
+    #[test]
+    fn legal_moves_en_passant() {
+        // taking en passant would expose the king along the fifth rank
+        assert_eq!(4, legal_moves_count("8/8/8/KPp4r/8/8/8/4k3 w - c6 0 1"));
+        // taking en passant removes the checking pawn
+        assert_eq!(9, legal_moves_count("8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1"));
+    }
 }
@@ -0,0 +1,431 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::engine::board::Board;
+use crate::engine::fen::FenError;
+use crate::engine::r#move::Move;
+use crate::engine::san::SanError;
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum EpdError {
+    Fen(FenError),
+    // a letter followed by at most 14 letters, digits or underscores
+    InvalidOpcode(String),
+    DuplicateOpcode(String),
+    UnterminatedString(String),
+    // hmvc or fmvn not being a number
+    InvalidMoveCounter(String),
+}
+
+impl fmt::Display for EpdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EpdError::Fen(e) => write!(f, "{}", e),
+            EpdError::InvalidOpcode(s) => write!(f, "invalid opcode '{}'", s),
+            EpdError::DuplicateOpcode(s) => write!(f, "opcode '{}' given twice", s),
+            EpdError::UnterminatedString(s) => {
+                write!(f, "unterminated string operand of '{}'", s)
+            }
+            EpdError::InvalidMoveCounter(s) => write!(f, "invalid move counter '{}'", s),
+        }
+    }
+}
+
+impl std::error::Error for EpdError {}
+
+impl From<FenError> for EpdError {
+    fn from(e: FenError) -> Self {
+        EpdError::Fen(e)
+    }
+}
+
+fn is_valid_opcode(opcode: &str) -> bool {
+    opcode.len() <= 15
+        && opcode.starts_with(|c: char| c.is_ascii_alphabetic())
+        && opcode
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// opcodes whose operands are written as strings, even without spaces
+fn has_string_operands(opcode: &str) -> bool {
+    opcode == "id"
+        || (opcode.len() == 2
+            && opcode.starts_with(['c', 'v'])
+            && opcode.as_bytes()[1].is_ascii_digit())
+}
+
+// a position with its operations, such as
+// `r1b1k2r/... w kq - bm Nxe5; id "WAC.002";`
+#[derive(Clone, Debug)]
+pub struct Epd {
+    pub board: Board,
+    // operands of each opcode, strings being stored without their quotes and
+    // backslash escapes
+    pub operations: BTreeMap<String, Vec<String>>,
+}
+
+impl Epd {
+    pub fn new(board: Board) -> Self {
+        Epd {
+            board,
+            operations: BTreeMap::new(),
+        }
+    }
+
+    pub fn operation(&self, opcode: &str) -> Option<&[String]> {
+        self.operations.get(opcode).map(Vec::as_slice)
+    }
+
+    pub fn set_operation(&mut self, opcode: &str, operands: Vec<String>) {
+        self.operations.insert(opcode.to_string(), operands);
+    }
+
+    fn single(&self, opcode: &str) -> Option<&str> {
+        self.operation(opcode)?.first().map(String::as_str)
+    }
+
+    fn moves(&self, opcode: &str) -> Result<Vec<Move>, SanError> {
+        self.operation(opcode)
+            .unwrap_or_default()
+            .iter()
+            .map(|san| self.board.parse_san(san))
+            .collect()
+    }
+
+    pub fn id(&self) -> Option<&str> {
+        self.single("id")
+    }
+
+    // primary comment
+    pub fn comment(&self) -> Option<&str> {
+        self.single("c0")
+    }
+
+    // best moves, none if there is no bm operation
+    pub fn best_moves(&self) -> Result<Vec<Move>, SanError> {
+        self.moves("bm")
+    }
+
+    // moves to avoid, none if there is no am operation
+    pub fn avoid_moves(&self) -> Result<Vec<Move>, SanError> {
+        self.moves("am")
+    }
+
+    // centipawn evaluation, from the side to move point of view
+    pub fn centipawns(&self) -> Option<i32> {
+        self.single("ce")?.parse().ok()
+    }
+
+    // analysis count depth, in plies
+    pub fn depth(&self) -> Option<u32> {
+        self.single("acd")?.parse().ok()
+    }
+
+    pub fn set_best_moves(&mut self, moves: &[Move]) {
+        let sans = moves.iter().map(|m| self.board.to_san(m)).collect();
+        self.set_operation("bm", sans);
+    }
+
+    pub fn set_avoid_moves(&mut self, moves: &[Move]) {
+        let sans = moves.iter().map(|m| self.board.to_san(m)).collect();
+        self.set_operation("am", sans);
+    }
+}
+
+// operations after the four FEN fields, a missing final semicolon being
+// accepted
+fn parse_operations(s: &str) -> Result<BTreeMap<String, Vec<String>>, EpdError> {
+    let mut operations = BTreeMap::new();
+    let mut chars = s.chars().peekable();
+
+    loop {
+        while chars.next_if(|c| c.is_whitespace() || *c == ';').is_some() {}
+        if chars.peek().is_none() {
+            return Ok(operations);
+        }
+
+        let mut opcode = String::new();
+        while let Some(c) = chars.next_if(|c| !c.is_whitespace() && *c != ';') {
+            opcode.push(c);
+        }
+        if !is_valid_opcode(&opcode) {
+            return Err(EpdError::InvalidOpcode(opcode));
+        }
+
+        let mut operands = Vec::new();
+        loop {
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+            match chars.next() {
+                None | Some(';') => break,
+                Some('"') => {
+                    let mut operand = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('"') => break,
+                            Some('\\') => match chars.next() {
+                                Some(c) => operand.push(c),
+                                None => return Err(EpdError::UnterminatedString(opcode)),
+                            },
+                            Some(c) => operand.push(c),
+                            None => return Err(EpdError::UnterminatedString(opcode)),
+                        }
+                    }
+                    operands.push(operand);
+                }
+                Some(c) => {
+                    let mut operand = String::from(c);
+                    while let Some(c) = chars.next_if(|c| !c.is_whitespace() && *c != ';') {
+                        operand.push(c);
+                    }
+                    operands.push(operand);
+                }
+            }
+        }
+
+        if operations.contains_key(&opcode) {
+            return Err(EpdError::DuplicateOpcode(opcode));
+        }
+        operations.insert(opcode, operands);
+    }
+}
+
+// the first word of s and what follows it, a move counter possibly being
+// followed by a semicolon
+fn split_counter(s: &str) -> (&str, &str) {
+    let s = s.trim_start();
+    s.split_once(|c: char| c.is_whitespace() || c == ';')
+        .unwrap_or((s, ""))
+}
+
+impl FromStr for Epd {
+    type Err = EpdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // the four fields of a FEN without its move counters
+        let mut rest = s.trim_start();
+        let mut fields = Vec::new();
+        for _ in 0..4 {
+            let (field, tail) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+            if !field.is_empty() {
+                fields.push(field);
+            }
+            rest = tail.trim_start();
+        }
+
+        // a full FEN, whose two move counters stand for hmvc and fmvn
+        let is_number = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+        let mut counters = None;
+        let (halfmove, tail) = split_counter(rest);
+        let (fullmove, tail) = split_counter(tail);
+        if is_number(halfmove) && is_number(fullmove) {
+            counters = Some([halfmove, fullmove]);
+            rest = tail;
+        }
+
+        let operations = parse_operations(rest)?;
+
+        // the counters come from the full FEN or from the operations
+        let mut fen = fields.join(" ");
+        if fields.len() == 4 {
+            for (i, (opcode, default)) in [("hmvc", "0"), ("fmvn", "1")].into_iter().enumerate() {
+                let field = counters.map(|counters| counters[i]);
+                let counter = match (operations.get(opcode).map(Vec::as_slice), field) {
+                    (None, Some(field)) => field,
+                    (None, None) => default,
+                    (Some(_), Some(_)) => {
+                        return Err(EpdError::DuplicateOpcode(opcode.to_string()))
+                    }
+                    (Some([n]), None) if n.parse::<u32>().is_ok() => n.as_str(),
+                    (Some(_), None) => {
+                        return Err(EpdError::InvalidMoveCounter(opcode.to_string()))
+                    }
+                };
+                fen.push(' ');
+                fen.push_str(counter);
+            }
+        }
+
+        Ok(Epd {
+            board: Board::from_fen(&fen)?,
+            operations,
+        })
+    }
+}
+
+impl fmt::Display for Epd {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fen = self.board.to_fen();
+        let fields: Vec<_> = fen.split(' ').take(4).collect();
+        write!(f, "{}", fields.join(" "))?;
+
+        // the move counters the four fields leave out, unless they are the
+        // defaults
+        let mut operations = self.operations.clone();
+        let halfmove = self.board.halfmove_clock();
+        let fullmove = self.board.fullmove_number();
+        if halfmove != 0 {
+            operations.insert("hmvc".to_string(), vec![halfmove.to_string()]);
+        }
+        if fullmove != 1 {
+            operations.insert("fmvn".to_string(), vec![fullmove.to_string()]);
+        }
+
+        for (opcode, operands) in &operations {
+            write!(f, " {}", opcode)?;
+            for operand in operands {
+                let quoted = has_string_operands(opcode)
+                    || operand.is_empty()
+                    || operand.contains(|c: char| c.is_whitespace() || c == ';' || c == '"');
+                if quoted {
+                    let escaped = operand.replace('\\', "\\\\").replace('"', "\\\"");
+                    write!(f, " \"{}\"", escaped)?;
+                } else {
+                    write!(f, " {}", operand)?;
+                }
+            }
+            write!(f, ";")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::fen::FenField;
+
+    const WAC_002: &str = r#"8/7p/5k2/5p2/p1p2P2/Pr1pPK2/1P1R3P/8 b - - bm Rxb2; id "WAC.002"; c0 "a comment; with semicolon"; ce -32; acd 12;"#;
+
+    #[test]
+    fn parse_operations() {
+        let epd: Epd = WAC_002.parse().unwrap();
+        assert_eq!(Some("WAC.002"), epd.id());
+        assert_eq!(Some("a comment; with semicolon"), epd.comment());
+        assert_eq!(Some(-32), epd.centipawns());
+        assert_eq!(Some(12), epd.depth());
+        assert_eq!(Some(&["Rxb2".to_string()][..]), epd.operation("bm"));
+
+        let best = epd.best_moves().unwrap();
+        assert_eq!(1, best.len());
+        assert_eq!("b3b2", best[0].to_uci());
+        assert!(best[0].is_capture);
+        assert_eq!(Ok(vec![]), epd.avoid_moves());
+        assert_eq!(1, epd.board.fullmove_number());
+
+        let epd: Epd = "4k3/8/8/8/8/8/8/R3K2R w KQ - am O-O Rb1 ;bm Ra8+;"
+            .parse()
+            .unwrap();
+        let avoid: Vec<_> = epd
+            .avoid_moves()
+            .unwrap()
+            .iter()
+            .map(Move::to_uci)
+            .collect();
+        assert_eq!(vec!["e1g1", "a1b1"], avoid);
+        assert_eq!(None, epd.id());
+        assert_eq!(None, epd.centipawns());
+    }
+
+    #[test]
+    fn write_sorted() {
+        let epd: Epd = WAC_002.parse().unwrap();
+        assert_eq!(
+            r#"8/7p/5k2/5p2/p1p2P2/Pr1pPK2/1P1R3P/8 b - - acd 12; bm Rxb2; c0 "a comment; with semicolon"; ce -32; id "WAC.002";"#,
+            epd.to_string()
+        );
+
+        let round_trip: Epd = epd.to_string().parse().unwrap();
+        assert_eq!(epd.operations, round_trip.operations);
+
+        // quotes and backslashes in strings are escaped
+        let mut epd = Epd::new(Board::new());
+        epd.set_operation("id", vec![r#"say "hi" \ bye"#.to_string()]);
+        epd.set_operation("pv", vec![r#"a"b"#.to_string()]);
+        assert_eq!(
+            r#"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - id "say \"hi\" \\ bye"; pv "a\"b";"#,
+            epd.to_string()
+        );
+        let round_trip: Epd = epd.to_string().parse().unwrap();
+        assert_eq!(epd.operations, round_trip.operations);
+
+        let mut epd = Epd::new(Board::new());
+        let e4 = epd.board.parse_san("e4").unwrap();
+        let nf3 = epd.board.parse_san("Nf3").unwrap();
+        epd.set_best_moves(&[e4, nf3]);
+        epd.set_operation("D1", vec!["20".to_string()]);
+        epd.set_operation("noop", vec![]);
+        assert_eq!(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - D1 20; bm e4 Nf3; noop;",
+            epd.to_string()
+        );
+    }
+
+    #[test]
+    fn move_counters() {
+        let epd: Epd = "4k3/8/8/8/8/8/8/4K3 w - - hmvc 7; fmvn 42;"
+            .parse()
+            .unwrap();
+        assert_eq!(7, epd.board.halfmove_clock());
+        assert_eq!(42, epd.board.fullmove_number());
+        assert_eq!(
+            "4k3/8/8/8/8/8/8/4K3 w - - fmvn 42; hmvc 7;",
+            epd.to_string()
+        );
+
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/4K3 b - - 3 20").unwrap();
+        let epd: Epd = Epd::new(board).to_string().parse().unwrap();
+        assert_eq!("4k3/8/8/8/8/8/8/4K3 b - - 3 20", epd.board.to_fen());
+
+        // a full FEN, its counters being read as hmvc and fmvn
+        let epd: Epd = "4k3/8/8/8/8/8/8/4K3 b - - 3 20; bm Kd7;".parse().unwrap();
+        assert_eq!("4k3/8/8/8/8/8/8/4K3 b - - 3 20", epd.board.to_fen());
+        assert_eq!(1, epd.operations.len());
+        let epd: Epd = "4k3/8/8/8/8/8/8/4K3 w - - 0 1".parse().unwrap();
+        assert!(epd.operations.is_empty());
+
+        // the perft suite writes a semicolon before each operation
+        let epd: Epd = "4k3/8/8/8/8/8/8/4K3 w - - ;D1 5 ;D2 25".parse().unwrap();
+        assert_eq!(Some(&["25".to_string()][..]), epd.operation("D2"));
+        assert_eq!(2, epd.operations.len());
+    }
+
+    #[test]
+    fn epd_errors() {
+        let parse = |s: &str| s.parse::<Epd>().unwrap_err();
+        let kings = "4k3/8/8/8/8/8/8/4K3 w - -";
+
+        assert_eq!(
+            EpdError::Fen(FenError::MissingField(FenField::EnPassant)),
+            parse("4k3/8/8/8/8/8/8/4K3 w -")
+        );
+        assert_eq!(
+            EpdError::InvalidOpcode("1bm".to_string()),
+            parse(&format!("{} 1bm e4;", kings))
+        );
+        assert_eq!(
+            EpdError::DuplicateOpcode("id".to_string()),
+            parse(&format!("{} id \"a\"; id \"b\";", kings))
+        );
+        assert_eq!(
+            EpdError::UnterminatedString("id".to_string()),
+            parse(&format!("{} id \"a;", kings))
+        );
+        assert_eq!(
+            EpdError::InvalidMoveCounter("fmvn".to_string()),
+            parse(&format!("{} fmvn x;", kings))
+        );
+        assert_eq!(
+            EpdError::DuplicateOpcode("hmvc".to_string()),
+            parse(&format!("{} 0 1 hmvc 2;", kings))
+        );
+
+        let epd: Epd = format!("{} bm Kd2 Kd9;", kings).parse().unwrap();
+        assert_eq!(
+            Err(SanError::InvalidFormat("Kd9".to_string())),
+            epd.best_moves()
+        );
+    }
+}
@@ -0,0 +1,33 @@
+use std::fmt;
+
+// failed conversion of untrusted input into one of the engine's value types
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum ConversionError {
+    ColorIndex(usize),
+    PieceIndex(usize),
+    RankIndex(usize),
+    FileIndex(usize),
+    SquareIndex(usize),
+    EmptyBitboard,
+    Color(String),
+    Piece(String),
+    Square(String),
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConversionError::ColorIndex(i) => write!(f, "no color with index {}", i),
+            ConversionError::PieceIndex(i) => write!(f, "no piece type with index {}", i),
+            ConversionError::RankIndex(i) => write!(f, "no rank with index {}", i),
+            ConversionError::FileIndex(i) => write!(f, "no file with index {}", i),
+            ConversionError::SquareIndex(i) => write!(f, "no square with index {}", i),
+            ConversionError::EmptyBitboard => write!(f, "empty bitboard has no square"),
+            ConversionError::Color(s) => write!(f, "invalid color '{}', expected w or b", s),
+            ConversionError::Piece(s) => write!(f, "invalid piece '{}'", s),
+            ConversionError::Square(s) => write!(f, "invalid square '{}'", s),
+        }
+    }
+}
+
+impl std::error::Error for ConversionError {}
@@ -0,0 +1,622 @@
+use crate::engine::bitboard::Bitboard;
+use crate::engine::board::{Board, COLOR_SWITCH};
+use crate::engine::piece::{Color, PieceType};
+use crate::engine::position::{File, Rank};
+use crate::engine::rules_bb::BACK_RANKS;
+use crate::engine::square::Square;
+
+pub const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum FenField {
+    PiecePlacement,
+    SideToMove,
+    Castling,
+    EnPassant,
+    HalfmoveClock,
+    FullmoveNumber,
+}
+
+impl std::fmt::Display for FenField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            FenField::PiecePlacement => "piece placement",
+            FenField::SideToMove => "side to move",
+            FenField::Castling => "castling availability",
+            FenField::EnPassant => "en passant target square",
+            FenField::HalfmoveClock => "halfmove clock",
+            FenField::FullmoveNumber => "fullmove number",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum FenError {
+    MissingField(FenField),
+    TooManyFields,
+    InvalidChar { field: FenField, c: char },
+    // ranks are counted from 8 down to 1, as they appear in the string
+    InvalidRankLength { rank: usize },
+    InvalidRankCount(usize),
+    InvalidKingCount(Color),
+    // more than 16 pieces or 8 pawns
+    TooManyPieces(Color),
+    TooManyPawns(Color),
+    // more pieces beyond the initial ones than pawns could have promoted
+    TooManyPromotedPieces(Color),
+    PawnOnBackRank(Square),
+    // a castling right whose king or rook is not on its initial square
+    InvalidCastlingRight(char),
+    // not right behind a pawn that could just have been pushed two squares
+    InvalidEnPassant(Square),
+    // the king of the side not to move could be captured
+    OpponentInCheck,
+    InvalidNumber(FenField),
+}
+
+impl std::fmt::Display for FenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FenError::MissingField(field) => write!(f, "missing {} field", field),
+            FenError::TooManyFields => write!(f, "too many fields"),
+            FenError::InvalidChar { field, c } => {
+                write!(f, "invalid character '{}' in {} field", c, field)
+            }
+            FenError::InvalidRankLength { rank } => {
+                write!(f, "rank {} does not describe exactly 8 squares", rank)
+            }
+            FenError::InvalidRankCount(n) => write!(f, "expected 8 ranks, got {}", n),
+            FenError::InvalidKingCount(color) => {
+                write!(f, "expected exactly one {:?} king", color)
+            }
+            FenError::TooManyPieces(color) => write!(f, "more than 16 {:?} pieces", color),
+            FenError::TooManyPawns(color) => write!(f, "more than 8 {:?} pawns", color),
+            FenError::TooManyPromotedPieces(color) => {
+                write!(
+                    f,
+                    "too many promoted {:?} pieces for the missing pawns",
+                    color
+                )
+            }
+            FenError::PawnOnBackRank(sq) => write!(f, "pawn on back rank square {}", sq),
+            FenError::InvalidCastlingRight(c) => {
+                write!(
+                    f,
+                    "castling right '{}' without its king and rook at home",
+                    c
+                )
+            }
+            FenError::InvalidEnPassant(sq) => {
+                write!(f, "no pawn could just have passed en passant square {}", sq)
+            }
+            FenError::OpponentInCheck => write!(f, "the side not to move is in check"),
+            FenError::InvalidNumber(field) => write!(f, "number out of range in {} field", field),
+        }
+    }
+}
+
+impl std::error::Error for FenError {}
+
+fn piece_from_char(c: char) -> Option<(PieceType, Color)> {
+    let color = if c.is_ascii_uppercase() {
+        Color::White
+    } else {
+        Color::Black
+    };
+
+    PieceType::try_from(c).ok().map(|piece| (piece, color))
+}
+
+fn parse_placement(s: &str) -> Result<[Bitboard; 12], FenError> {
+    let mut bitboards = [Bitboard::EMPTY; 12];
+
+    let ranks: Vec<&str> = s.split('/').collect();
+    if ranks.len() != 8 {
+        return Err(FenError::InvalidRankCount(ranks.len()));
+    }
+
+    for (rank, rank_str) in Rank::ALL.into_iter().rev().zip(&ranks) {
+        let mut file = 0;
+
+        for c in rank_str.chars() {
+            if let Some(skip) = c.to_digit(10).filter(|d| (1..=8).contains(d)) {
+                file += skip as usize;
+            } else if let Some((piece, color)) = piece_from_char(c) {
+                if let Some(&f) = File::ALL.get(file) {
+                    bitboards[color as usize * COLOR_SWITCH + piece as usize]
+                        .toggle(Square::from_coords(rank, f));
+                }
+                file += 1;
+            } else {
+                return Err(FenError::InvalidChar {
+                    field: FenField::PiecePlacement,
+                    c,
+                });
+            }
+
+            if file > 8 {
+                return Err(FenError::InvalidRankLength {
+                    rank: rank as usize + 1,
+                });
+            }
+        }
+
+        if file != 8 {
+            return Err(FenError::InvalidRankLength {
+                rank: rank as usize + 1,
+            });
+        }
+    }
+
+    for color in [Color::White, Color::Black] {
+        let kings = bitboards[color as usize * COLOR_SWITCH + PieceType::King as usize];
+        if kings.popcount() != 1 {
+            return Err(FenError::InvalidKingCount(color));
+        }
+    }
+    check_material(&bitboards)?;
+
+    Ok(bitboards)
+}
+
+// piece counts that a game could reach, which also bounds the number of
+// moves a position can have
+fn check_material(bitboards: &[Bitboard; 12]) -> Result<(), FenError> {
+    for color in Color::ALL {
+        let count =
+            |piece: PieceType| bitboards[color as usize * COLOR_SWITCH + piece as usize].popcount();
+
+        let pieces: u32 = PieceType::ALL.iter().map(|p| count(*p)).sum();
+        if pieces > 16 {
+            return Err(FenError::TooManyPieces(color));
+        }
+        let pawns = count(PieceType::Pawn);
+        if pawns > 8 {
+            return Err(FenError::TooManyPawns(color));
+        }
+
+        let initial = [
+            (PieceType::Knight, 2),
+            (PieceType::Bishop, 2),
+            (PieceType::Rook, 2),
+            (PieceType::Queen, 1),
+        ];
+        let promoted: u32 = initial
+            .iter()
+            .map(|(piece, n)| count(*piece).saturating_sub(*n))
+            .sum();
+        if promoted > 8 - pawns {
+            return Err(FenError::TooManyPromotedPieces(color));
+        }
+
+        let pawns = bitboards[color as usize * COLOR_SWITCH + PieceType::Pawn as usize];
+        if let Some(sq) = (pawns & BACK_RANKS).next() {
+            return Err(FenError::PawnOnBackRank(sq));
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_en_passant(s: &str) -> Result<Option<Square>, FenError> {
+    if s == "-" {
+        return Ok(None);
+    }
+
+    let invalid = |c| FenError::InvalidChar {
+        field: FenField::EnPassant,
+        c,
+    };
+
+    let mut chars = s.chars();
+    let file = match chars.next() {
+        Some(c @ 'a'..='h') => File::ALL[c as usize - 'a' as usize],
+        Some(c) => return Err(invalid(c)),
+        None => return Err(FenError::MissingField(FenField::EnPassant)),
+    };
+    let rank = match chars.next() {
+        Some('3') => Rank::Three,
+        Some('6') => Rank::Six,
+        Some(c) => return Err(invalid(c)),
+        None => return Err(FenError::MissingField(FenField::EnPassant)),
+    };
+    if let Some(c) = chars.next() {
+        return Err(invalid(c));
+    }
+
+    Ok(Some(Square::from_coords(rank, file)))
+}
+
+fn parse_number(s: &str, field: FenField) -> Result<u32, FenError> {
+    if let Some(c) = s.chars().find(|c| !c.is_ascii_digit()) {
+        return Err(FenError::InvalidChar { field, c });
+    }
+
+    s.parse().map_err(|_| FenError::InvalidNumber(field))
+}
+
+impl Board {
+    pub fn from_fen(fen: &str) -> Result<Board, FenError> {
+        let mut fields = fen.split_whitespace();
+        let mut next_field = |field| fields.next().ok_or(FenError::MissingField(field));
+
+        let bitboards = parse_placement(next_field(FenField::PiecePlacement)?)?;
+
+        let s = next_field(FenField::SideToMove)?;
+        let side_to_move = s.parse::<Color>().map_err(|_| FenError::InvalidChar {
+            field: FenField::SideToMove,
+            c: s.chars().find(|c| *c != 'w' && *c != 'b').unwrap_or('w'),
+        })?;
+
+        let mut board = Board {
+            bitboards,
+            mailbox: Board::mailbox_from(&bitboards),
+            side_to_move,
+            turn: 0,
+            white_king_castling: false,
+            white_queen_castling: false,
+            black_king_castling: false,
+            black_queen_castling: false,
+            en_passant: None,
+            halfmove_clock: 0,
+            hash: 0,
+            history: Vec::new(),
+        };
+
+        let castling = next_field(FenField::Castling)?;
+        if castling != "-" {
+            for c in castling.chars() {
+                let invalid = FenError::InvalidChar {
+                    field: FenField::Castling,
+                    c,
+                };
+                let (flag, color, rank, rook_file) = match c {
+                    'K' => (
+                        &mut board.white_king_castling,
+                        Color::White,
+                        Rank::One,
+                        File::H,
+                    ),
+                    'Q' => (
+                        &mut board.white_queen_castling,
+                        Color::White,
+                        Rank::One,
+                        File::A,
+                    ),
+                    'k' => (
+                        &mut board.black_king_castling,
+                        Color::Black,
+                        Rank::Eight,
+                        File::H,
+                    ),
+                    'q' => (
+                        &mut board.black_queen_castling,
+                        Color::Black,
+                        Rank::Eight,
+                        File::A,
+                    ),
+                    _ => return Err(invalid),
+                };
+                if *flag {
+                    return Err(invalid);
+                }
+                *flag = true;
+
+                let king = Square::from_coords(rank, File::E);
+                let rook = Square::from_coords(rank, rook_file);
+                if board[king] != Some((PieceType::King, color))
+                    || board[rook] != Some((PieceType::Rook, color))
+                {
+                    return Err(FenError::InvalidCastlingRight(c));
+                }
+            }
+        }
+
+        board.en_passant = parse_en_passant(next_field(FenField::EnPassant)?)?;
+        if let Some(ep) = board.en_passant {
+            // the pushed pawn stands right in front of the target square
+            let (rank, pushed) = match side_to_move {
+                Color::White => (Rank::Six, ep.offset(-8)),
+                Color::Black => (Rank::Three, ep.offset(8)),
+            };
+            let enemy_pawn = Some((PieceType::Pawn, !side_to_move));
+            if ep.rank() != rank || board[ep].is_some() || board[pushed] != enemy_pawn {
+                return Err(FenError::InvalidEnPassant(ep));
+            }
+        }
+
+        // the move counters are commonly omitted (e.g. in EPD), default them
+        let halfmove = fields.next();
+        let fullmove = fields.next();
+        if fields.next().is_some() {
+            return Err(FenError::TooManyFields);
+        }
+
+        if let Some(s) = halfmove {
+            board.halfmove_clock = parse_number(s, FenField::HalfmoveClock)?;
+        }
+        let fullmove = match fullmove {
+            Some(s) => parse_number(s, FenField::FullmoveNumber)?.max(1),
+            None => 1,
+        };
+        board.turn = match side_to_move {
+            Color::White => fullmove - 1,
+            Color::Black => fullmove,
+        };
+        board.hash = board.compute_hash();
+
+        let their_king = board.king_square(!side_to_move);
+        let attackers = board.attackers_to(their_king, board.occupancy_bb());
+        if !(attackers & board.color_bb(side_to_move)).is_empty() {
+            return Err(FenError::OpponentInCheck);
+        }
+
+        Ok(board)
+    }
+
+    pub fn to_fen(&self) -> String {
+        let mut fen = String::new();
+
+        for rank in Rank::ALL.into_iter().rev() {
+            let mut empty = 0;
+            for file in File::ALL {
+                match self[Square::from_coords(rank, file)] {
+                    Some((piece, color)) => {
+                        if empty != 0 {
+                            fen.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        let c: char = piece.into();
+                        fen.push(match color {
+                            Color::White => c,
+                            Color::Black => c.to_ascii_lowercase(),
+                        });
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty != 0 {
+                fen.push_str(&empty.to_string());
+            }
+            if rank != Rank::One {
+                fen.push('/');
+            }
+        }
+
+        fen.push_str(match self.side_to_move {
+            Color::White => " w ",
+            Color::Black => " b ",
+        });
+
+        let castling: String = [
+            (self.white_king_castling, 'K'),
+            (self.white_queen_castling, 'Q'),
+            (self.black_king_castling, 'k'),
+            (self.black_queen_castling, 'q'),
+        ]
+        .iter()
+        .filter(|(flag, _)| *flag)
+        .map(|(_, c)| *c)
+        .collect();
+        fen.push_str(if castling.is_empty() { "-" } else { &castling });
+
+        fen.push(' ');
+        match self.en_passant {
+            Some(sq) => fen.push_str(&sq.to_string()),
+            None => fen.push('-'),
+        }
+
+        fen.push_str(&format!(
+            " {} {}",
+            self.halfmove_clock,
+            self.fullmove_number()
+        ));
+
+        fen
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn starting_position() {
+        let board = Board::from_fen(STARTING_FEN).unwrap();
+        assert_eq!(Board::new().bitboards, board.bitboards);
+        assert_eq!(STARTING_FEN, Board::new().to_fen());
+    }
+
+    #[test]
+    fn round_trip() {
+        let fens = [
+            STARTING_FEN,
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "8/8/8/3k4/3Pp3/8/8/3K4 b - d3 0 42",
+            "4k3/8/8/8/8/8/8/4K2R b K - 17 63",
+        ];
+
+        for fen in fens {
+            assert_eq!(fen, Board::from_fen(fen).unwrap().to_fen());
+        }
+    }
+
+    #[test]
+    fn fields() {
+        let board = Board::from_fen("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBN1 b Qk e3 5 12")
+            .unwrap();
+
+        assert_eq!(Color::Black, board.side_to_move);
+        assert!(!board.white_king_castling);
+        assert!(board.white_queen_castling);
+        assert!(board.black_king_castling);
+        assert!(!board.black_queen_castling);
+        assert_eq!(Some(Square::new(20)), board.en_passant);
+        assert_eq!(5, board.halfmove_clock);
+        assert_eq!(12, board.fullmove_number());
+    }
+
+    #[test]
+    fn optional_move_counters() {
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - -").unwrap();
+        assert_eq!("4k3/8/8/8/8/8/8/4K3 w - - 0 1", board.to_fen());
+    }
+
+    #[test]
+    fn errors() {
+        use FenError::*;
+        use FenField::*;
+
+        let cases = [
+            ("", MissingField(PiecePlacement)),
+            ("4k3/8/8/8/8/8/8/4K3", MissingField(SideToMove)),
+            ("4k3/8/8/8/8/8/8/4K3 w", MissingField(Castling)),
+            ("4k3/8/8/8/8/8/8/4K3 w -", MissingField(EnPassant)),
+            ("4k3/8/8/8/8/8/4K3 w - - 0 1", InvalidRankCount(7)),
+            (
+                "4k3/8/8/8/8/8/8/4K2 w - - 0 1",
+                InvalidRankLength { rank: 1 },
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4K4 w - - 0 1",
+                InvalidRankLength { rank: 1 },
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4X3 w - - 0 1",
+                InvalidChar {
+                    field: PiecePlacement,
+                    c: 'X',
+                },
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4K3 x - - 0 1",
+                InvalidChar {
+                    field: SideToMove,
+                    c: 'x',
+                },
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4K2R w KX - 0 1",
+                InvalidChar {
+                    field: Castling,
+                    c: 'X',
+                },
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4K2R w KK - 0 1",
+                InvalidChar {
+                    field: Castling,
+                    c: 'K',
+                },
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4K3 w - e4 0 1",
+                InvalidChar {
+                    field: EnPassant,
+                    c: '4',
+                },
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4K3 w - i3 0 1",
+                InvalidChar {
+                    field: EnPassant,
+                    c: 'i',
+                },
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4K3 w - - a 1",
+                InvalidChar {
+                    field: HalfmoveClock,
+                    c: 'a',
+                },
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4K3 w - - 0 -1",
+                InvalidChar {
+                    field: FullmoveNumber,
+                    c: '-',
+                },
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4K3 w - - 99999999999 1",
+                InvalidNumber(HalfmoveClock),
+            ),
+            ("4k3/8/8/8/8/8/8/4K3 w - - 0 1 x", TooManyFields),
+            (
+                "8/8/8/8/8/8/8/4K3 w - - 0 1",
+                InvalidKingCount(Color::Black),
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4KK2 w - - 0 1",
+                InvalidKingCount(Color::White),
+            ),
+            (
+                "4k3/pppppppp/pppppppp/8/8/8/8/4K3 b - - 0 1",
+                TooManyPieces(Color::Black),
+            ),
+            (
+                "4k3/8/8/8/8/1P6/PPPPPPPP/4K3 w - - 0 1",
+                TooManyPawns(Color::White),
+            ),
+            (
+                "4k3/8/8/8/8/8/PPPPPPPP/QQ2K3 w - - 0 1",
+                TooManyPromotedPieces(Color::White),
+            ),
+            (
+                "1QQQQQ2/Q4QQQ/Q6R/Q6Q/Q3Q2Q/Q6Q/Q2Q3Q/kQRQ1QQK w - - 0 1",
+                TooManyPieces(Color::White),
+            ),
+            (
+                "4k2P/8/8/8/8/8/8/4K3 w - - 0 1",
+                PawnOnBackRank(Square::new(63)),
+            ),
+            (
+                "4k3/8/8/8/8/8/8/p3K3 w - - 0 1",
+                PawnOnBackRank(Square::new(0)),
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4K3 w KQkq - 0 1",
+                InvalidCastlingRight('K'),
+            ),
+            (
+                "r3k3/8/8/8/8/8/8/R3K2R w KQkq - 0 1",
+                InvalidCastlingRight('k'),
+            ),
+            (
+                "r3k2r/8/8/8/8/8/8/R4K1R w Qq - 0 1",
+                InvalidCastlingRight('Q'),
+            ),
+            (
+                "4k3/8/8/8/8/8/3PP3/4K3 w - e3 0 1",
+                InvalidEnPassant(Square::new(20)),
+            ),
+            (
+                "4k3/8/8/4p3/8/8/8/4K3 b - e6 0 1",
+                InvalidEnPassant(Square::new(44)),
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4K3 w - e6 0 1",
+                InvalidEnPassant(Square::new(44)),
+            ),
+            (
+                "4k3/8/4n3/4p3/8/8/8/4K3 w - e6 0 1",
+                InvalidEnPassant(Square::new(44)),
+            ),
+            (
+                "4k3/8/8/4P3/8/8/8/4K3 w - e6 0 1",
+                InvalidEnPassant(Square::new(44)),
+            ),
+            ("4k3/4R3/8/8/8/8/8/4K3 w - - 0 1", OpponentInCheck),
+            ("4k3/8/8/8/8/8/3p4/4K3 b - - 0 1", OpponentInCheck),
+        ];
+
+        for (fen, err) in cases {
+            assert_eq!(Some(err), Board::from_fen(fen).err(), "{}", fen);
+        }
+    }
+}
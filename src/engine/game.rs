@@ -0,0 +1,286 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::engine::board::Board;
+use crate::engine::fen::FenError;
+use crate::engine::r#move::Move;
+
+// tags every exported game has, in this order, with their unknown values
+pub const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [
+    ("Event", "?"),
+    ("Site", "?"),
+    ("Date", "????.??.??"),
+    ("Round", "?"),
+    ("White", "?"),
+    ("Black", "?"),
+    ("Result", "*"),
+];
+
+// engine evaluation from the point of view of white, as in [%eval 0.25] or
+// [%eval #-3]
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Eval {
+    Centipawns(i32),
+    // moves until mate, negative when black mates
+    Mate(i32),
+}
+
+impl Eval {
+    fn parse(s: &str) -> Option<Self> {
+        match s.strip_prefix('#') {
+            Some(mate) => mate.parse().ok().map(Eval::Mate),
+            None => {
+                let pawns: f64 = s.parse().ok()?;
+                pawns
+                    .is_finite()
+                    .then(|| Eval::Centipawns((pawns * 100.0).round() as i32))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Eval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Eval::Centipawns(cp) => {
+                let sign = if cp < 0 { "-" } else { "" };
+                write!(
+                    f,
+                    "{}{}.{:02}",
+                    sign,
+                    cp.unsigned_abs() / 100,
+                    cp.unsigned_abs() % 100
+                )
+            }
+            Eval::Mate(n) => write!(f, "#{}", n),
+        }
+    }
+}
+
+// h:mm:ss with optional tenths, as in [%clk 1:05:09.5]
+fn parse_clock(s: &str) -> Option<Duration> {
+    let mut fields = s.split(':');
+    let hours: u64 = fields.next()?.parse().ok()?;
+    let minutes: u64 = fields.next()?.parse().ok()?;
+    let seconds: f64 = fields.next()?.parse().ok()?;
+    if fields.next().is_some() || minutes >= 60 || !(0.0..60.0).contains(&seconds) {
+        return None;
+    }
+
+    let secs = hours.checked_mul(3600)?.checked_add(minutes * 60)?;
+    Duration::from_secs(secs).checked_add(Duration::from_secs_f64(seconds))
+}
+
+fn format_clock(clock: Duration) -> String {
+    let secs = clock.as_secs();
+    let mut s = format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60);
+    let tenths = clock.subsec_millis() / 100;
+    if tenths != 0 {
+        s.push_str(&format!(".{}", tenths));
+    }
+
+    s
+}
+
+// a move of the game with its annotations and the lines played instead of it
+#[derive(PartialEq, Clone, Debug)]
+pub struct MoveNode {
+    pub m: Move,
+    // numeric annotation glyphs, 1 standing for !, 2 for ? ...
+    pub nags: Vec<u8>,
+    pub comment_before: Option<String>,
+    pub comment: Option<String>,
+    pub clock: Option<Duration>,
+    pub eval: Option<Eval>,
+    pub variations: Vec<Vec<MoveNode>>,
+}
+
+impl MoveNode {
+    pub fn new(m: Move) -> Self {
+        MoveNode {
+            m,
+            nags: Vec::new(),
+            comment_before: None,
+            comment: None,
+            clock: None,
+            eval: None,
+            variations: Vec::new(),
+        }
+    }
+
+    // adds the text of a comment following the move, taking out the [%clk]
+    // and [%eval] commands it holds
+    pub fn add_comment(&mut self, text: &str) {
+        let mut rest = String::new();
+        let mut text = text;
+
+        while let Some(start) = text.find("[%") {
+            let Some(len) = text[start..].find(']') else {
+                break;
+            };
+            let command = &text[start + 2..start + len];
+            let (name, args) = command.split_once(' ').unwrap_or((command, ""));
+            let parsed = match name {
+                "clk" => parse_clock(args.trim()).map(|clock| self.clock = Some(clock)),
+                "eval" => Eval::parse(args.trim()).map(|eval| self.eval = Some(eval)),
+                _ => None,
+            };
+
+            let end = start + len + 1;
+            rest.push_str(&text[..start]);
+            if parsed.is_none() {
+                rest.push_str(&text[start..end]);
+            }
+            text = &text[end..];
+        }
+        rest.push_str(text);
+
+        let rest = rest.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !rest.is_empty() {
+            self.comment = Some(match self.comment.take() {
+                Some(comment) => comment + " " + &rest,
+                None => rest,
+            });
+        }
+    }
+
+    // the comment as written after the move, commands included
+    pub fn full_comment(&self) -> Option<String> {
+        let mut parts = Vec::new();
+        if let Some(clock) = self.clock {
+            parts.push(format!("[%clk {}]", format_clock(clock)));
+        }
+        if let Some(eval) = self.eval {
+            parts.push(format!("[%eval {}]", eval));
+        }
+        parts.extend(self.comment.clone());
+
+        (!parts.is_empty()).then(|| parts.join(" "))
+    }
+}
+
+// a game with its tag pairs, its mainline and the variations branching from it
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct Game {
+    // in the order of the file
+    pub tags: Vec<(String, String)>,
+    pub mainline: Vec<MoveNode>,
+}
+
+impl Game {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // a game starting from `board`, recorded with the SetUp and FEN tags
+    pub fn from_board(board: &Board) -> Self {
+        let mut game = Game::new();
+        game.set_tag("SetUp", "1");
+        game.set_tag("FEN", &board.to_fen());
+        game
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+            Some((_, old)) => *old = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    // the position given by the FEN tag, the initial one otherwise
+    pub fn starting_board(&self) -> Result<Board, FenError> {
+        match self.tag("FEN") {
+            Some(fen) => Board::from_fen(fen),
+            None => Ok(Board::new()),
+        }
+    }
+
+    pub fn moves(&self) -> impl Iterator<Item = &Move> {
+        self.mainline.iter().map(|node| &node.m)
+    }
+
+    // m is only checked against the position when the game is written
+    pub fn push(&mut self, m: Move) -> &mut MoveNode {
+        self.mainline.push(MoveNode::new(m));
+        self.mainline.last_mut().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::piece::PieceType;
+    use crate::engine::square::Square;
+
+    fn e4() -> MoveNode {
+        let start = Square::new(12).into();
+        let end = Square::new(28).into();
+        MoveNode::new(Move::new(start, end, PieceType::Pawn))
+    }
+
+    #[test]
+    fn comment_commands() {
+        let mut node = e4();
+        node.add_comment("[%clk 1:05:09.5] best [%eval -0.3] [%csl Ga4]  move");
+        assert_eq!(Some(Duration::from_millis(3909500)), node.clock);
+        assert_eq!(Some(Eval::Centipawns(-30)), node.eval);
+        assert_eq!(Some("best [%csl Ga4] move"), node.comment.as_deref());
+        assert_eq!(
+            Some("[%clk 1:05:09.5] [%eval -0.30] best [%csl Ga4] move".to_string()),
+            node.full_comment()
+        );
+
+        let mut node = e4();
+        node.add_comment("[%eval #-3][%clk 0:00:07]");
+        assert_eq!(Some(Eval::Mate(-3)), node.eval);
+        assert_eq!(None, node.comment);
+        assert_eq!("[%clk 0:00:07] [%eval #-3]", node.full_comment().unwrap());
+
+        // malformed commands are kept as text
+        let mut node = e4();
+        node.add_comment("[%clk 5:61:00] [%eval");
+        assert_eq!(None, node.clock);
+        assert_eq!(Some("[%clk 5:61:00] [%eval"), node.comment.as_deref());
+        assert_eq!(None, e4().full_comment());
+    }
+
+    #[test]
+    fn evals_and_clocks() {
+        assert_eq!("0.25", Eval::Centipawns(25).to_string());
+        assert_eq!("-0.05", Eval::Centipawns(-5).to_string());
+        assert_eq!("3.00", Eval::Centipawns(300).to_string());
+        assert_eq!(Some(Eval::Centipawns(-150)), Eval::parse("-1.5"));
+        assert_eq!(None, Eval::parse("NaN"));
+        assert_eq!("0:03:21", format_clock(Duration::from_secs(201)));
+        assert_eq!(Some(Duration::from_secs(201)), parse_clock("0:03:21"));
+
+        // overflows are no clock rather than a panic
+        assert_eq!(None, parse_clock("10000000000000000:00:00"));
+        assert_eq!(None, parse_clock("5124095576030431:59:00"));
+        assert_eq!(None, parse_clock("5124095576030431:00:16"));
+        let min = Eval::parse("-21474836.48").unwrap();
+        assert_eq!(Eval::Centipawns(i32::MIN), min);
+        assert_eq!("-21474836.48", min.to_string());
+    }
+
+    #[test]
+    fn tags() {
+        let mut game = Game::from_board(&Board::new());
+        assert_eq!(Some("1"), game.tag("SetUp"));
+        game.set_tag("White", "Fischer");
+        game.set_tag("White", "Spassky");
+        assert_eq!(Some("Spassky"), game.tag("White"));
+        assert_eq!(3, game.tags.len());
+        assert_eq!(
+            Board::new().to_fen(),
+            game.starting_board().unwrap().to_fen()
+        );
+    }
+}
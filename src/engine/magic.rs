@@ -0,0 +1,274 @@
+use std::sync::OnceLock;
+
+use crate::engine::rules_bb::{bishop_attacks_slow, rook_attacks_slow};
+
+// fixed-shift "plain" magic bitboards: the relevant blockers of a square are
+// mapped by a multiplication to an index in a table of precomputed attacks.
+// The magic numbers were found with a trial and error search, only the
+// attack tables are filled at run time, on first use.
+
+const ROOK_MAGICS: [u64; 64] = [
+    0x2080002080400010,
+    0x00c0002001401000,
+    0x2100110008402002,
+    0x0880080081041000,
+    0x0200020020041008,
+    0x2300040008010012,
+    0x0c00283004008201,
+    0x0180010000407a80,
+    0x0168800080400020,
+    0x0010400040201000,
+    0x1001002001001048,
+    0x1001002408100100,
+    0x0801000408010012,
+    0x4001000209000400,
+    0x08a20004c8020001,
+    0x2002801145002280,
+    0x0080860021004200,
+    0x001000c009402002,
+    0x00b0002004002800,
+    0x100a808010020800,
+    0x8101010008000410,
+    0x0244008002000480,
+    0x0000040010810208,
+    0x2000020000448534,
+    0x4104400480008033,
+    0x0000810100204000,
+    0x0440430900200010,
+    0x4600240900100100,
+    0x0060080080040080,
+    0x0001000300080400,
+    0x0004084400011002,
+    0x0023040200008041,
+    0x0580050043002080,
+    0x0400804002802008,
+    0x0001002001004010,
+    0x1000200901001000,
+    0x4410800801800c00,
+    0xa012003806001004,
+    0x0020100104008802,
+    0x0004808402000041,
+    0x0010400170898000,
+    0x0080500020004004,
+    0x1040408012020020,
+    0x8010040008004040,
+    0x2001080100110004,
+    0x0000020004008080,
+    0x0021010810040002,
+    0x0800008c43020024,
+    0x0000800021005100,
+    0x0070201040008080,
+    0x0000d04282006a00,
+    0x0010014400080240,
+    0x0001080110050100,
+    0x0012000810240600,
+    0x0402000801040200,
+    0x028100108a004100,
+    0x0050800300102045,
+    0x8208210040120882,
+    0x8010600101183441,
+    0x020b000910006045,
+    0x0241001002480005,
+    0x0081000400880241,
+    0x0000009008024124,
+    0x0048122980410402,
+];
+const BISHOP_MAGICS: [u64; 64] = [
+    0x0848020822040013,
+    0x8010a40085821200,
+    0x0008008430840822,
+    0x0808048108040000,
+    0x1304042100008104,
+    0x5001012010204023,
+    0x81048801b8200420,
+    0x200a008084012000,
+    0x0040102001042084,
+    0x840a505042428020,
+    0x0000700102202920,
+    0x44101c0c10800002,
+    0x0040040422000000,
+    0x0180020802090202,
+    0x4020020811041202,
+    0x000104308c042000,
+    0x4140661002424400,
+    0x0028012008010460,
+    0x0188062102002a00,
+    0x0014004840102008,
+    0x0105000290400002,
+    0x8001022200410400,
+    0x104a041918013446,
+    0x008a000082008238,
+    0x04a0060008100430,
+    0x0008220008820801,
+    0x2508041208005010,
+    0x4008080200202020,
+    0x2441001013004000,
+    0x0030008060407000,
+    0x4008108000420800,
+    0x0012021050290100,
+    0x0210080482200500,
+    0xcc01112048100480,
+    0x0020402806500440,
+    0x00048e0080580080,
+    0x0040102020020080,
+    0x0028010440080807,
+    0x4601041108008800,
+    0x8040810e04104200,
+    0x901210110400088a,
+    0xa003080212081050,
+    0x00c1004048401004,
+    0x900000a014400800,
+    0x0008021040405401,
+    0x4020008206002090,
+    0x0004190424030100,
+    0x0424008a02026250,
+    0x8004088250900040,
+    0x1c00430088a04200,
+    0x0001020094040001,
+    0x8040210020880061,
+    0x2010040450442032,
+    0x0800840850044001,
+    0x0004040802140004,
+    0x0004080a04222020,
+    0x8088802110022000,
+    0x1081a10416114400,
+    0x0205010a24060820,
+    0x0000000720411080,
+    0x1008000208430400,
+    0x580c026028810840,
+    0x802020441020a110,
+    0x12c0022401020018,
+];
+
+const RANK_EDGES: u64 = 0xff000000000000ff;
+const FILE_EDGES: u64 = 0x8181818181818181;
+
+struct Magic {
+    mask: u64,
+    magic: u64,
+    shift: u32,
+    offset: usize,
+}
+
+impl Magic {
+    fn index(&self, blockers: u64) -> usize {
+        self.offset + ((blockers & self.mask).wrapping_mul(self.magic) >> self.shift) as usize
+    }
+}
+
+pub(crate) struct SliderTable {
+    magics: Vec<Magic>,
+    attacks: Vec<u64>,
+}
+
+impl SliderTable {
+    fn new(magics: &[u64; 64], slow_attacks: fn(usize, u64) -> u64) -> Self {
+        let mut table = SliderTable {
+            magics: Vec::with_capacity(64),
+            attacks: Vec::new(),
+        };
+
+        for (sq, magic) in magics.iter().enumerate() {
+            let mask = relevant_blockers(sq, slow_attacks);
+            let magic = Magic {
+                mask,
+                magic: *magic,
+                shift: 64 - mask.count_ones(),
+                offset: table.attacks.len(),
+            };
+            table
+                .attacks
+                .resize(magic.offset + (1 << mask.count_ones()), 0);
+
+            // walk every subset of the mask (carry-rippler)
+            let mut blockers = 0u64;
+            loop {
+                table.attacks[magic.index(blockers)] = slow_attacks(sq, blockers);
+                blockers = blockers.wrapping_sub(mask) & mask;
+                if blockers == 0 {
+                    break;
+                }
+            }
+
+            table.magics.push(magic);
+        }
+
+        table
+    }
+
+    pub(crate) fn attacks(&self, sq: usize, blockers: u64) -> u64 {
+        self.attacks[self.magics[sq].index(blockers)]
+    }
+}
+
+// squares whose occupancy changes the attacks from sq: the edge of a ray is
+// attacked whether it holds a piece or not
+pub(crate) fn relevant_blockers(sq: usize, slow_attacks: fn(usize, u64) -> u64) -> u64 {
+    let rank = 0xffu64 << (sq / 8 * 8);
+    let file = 0x0101010101010101u64 << (sq % 8);
+    let edges = (RANK_EDGES & !rank) | (FILE_EDGES & !file);
+
+    slow_attacks(sq, 0) & !edges
+}
+
+pub(crate) fn rook_table() -> &'static SliderTable {
+    static TABLE: OnceLock<SliderTable> = OnceLock::new();
+    TABLE.get_or_init(|| SliderTable::new(&ROOK_MAGICS, rook_attacks_slow))
+}
+
+pub(crate) fn bishop_table() -> &'static SliderTable {
+    static TABLE: OnceLock<SliderTable> = OnceLock::new();
+    TABLE.get_or_init(|| SliderTable::new(&BISHOP_MAGICS, bishop_attacks_slow))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::bitboard::Bitboard;
+    use crate::engine::board::tests::Rng;
+    use crate::engine::rules_bb::{bishop_attacks_bb, rook_attacks_bb};
+    use crate::engine::square::Square;
+
+    fn check_all_subsets(fast: fn(usize, u64) -> u64, slow: fn(usize, u64) -> u64) {
+        let mut rng = Rng(0x853c49e6748fea9b);
+
+        for sq in 0..64 {
+            let mask = relevant_blockers(sq, slow);
+            let mut blockers = 0u64;
+            loop {
+                assert_eq!(
+                    slow(sq, blockers),
+                    fast(sq, blockers),
+                    "{} {:x}",
+                    sq,
+                    blockers
+                );
+
+                // squares out of the mask must not change anything
+                let noisy = blockers | (rng.next_u64() & !mask);
+                assert_eq!(slow(sq, noisy), fast(sq, noisy), "{} {:x}", sq, noisy);
+
+                blockers = blockers.wrapping_sub(mask) & mask;
+                if blockers == 0 {
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rook_attacks_all_blockers() {
+        check_all_subsets(
+            |sq, blockers| rook_attacks_bb(Square::new(sq), Bitboard(blockers)).0,
+            rook_attacks_slow,
+        );
+    }
+
+    #[test]
+    fn bishop_attacks_all_blockers() {
+        check_all_subsets(
+            |sq, blockers| bishop_attacks_bb(Square::new(sq), Bitboard(blockers)).0,
+            bishop_attacks_slow,
+        );
+    }
+}
@@ -1,13 +1,17 @@
+use std::fmt;
+
 use crate::engine::piece::PieceType;
 use crate::engine::position::Position;
+use crate::engine::square::Square;
 
-#[derive(Debug)]
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
 pub struct Move {
     pub start: Position,
     pub end: Position,
     pub piece_type: PieceType,
 
     pub is_capture: bool,
+    pub promotion: Option<PieceType>,
 }
 
 impl Move {
@@ -18,6 +22,164 @@ impl Move {
             piece_type,
 
             is_capture: false,
+            promotion: None,
         }
     }
+
+    pub fn with_promotion(mut self, piece_type: PieceType) -> Self {
+        self.promotion = Some(piece_type);
+        self
+    }
+
+    // long algebraic notation used by UCI, castling being a king move (e1g1)
+    pub fn to_uci(&self) -> String {
+        let mut uci = format!("{}{}", self.start, self.end);
+        if let Some(piece) = self.promotion {
+            uci.push(char::from(piece).to_ascii_lowercase());
+        }
+
+        uci
+    }
+}
+
+// from square (bits 0-5), to square (bits 6-11) and flags (bits 12-15), the
+// moving piece being whatever stands on the from square
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Default, Hash)]
+pub struct PackedMove(u16);
+
+impl PackedMove {
+    pub const QUIET: u16 = 0;
+    pub const DOUBLE_PUSH: u16 = 1;
+    pub const KING_CASTLE: u16 = 2;
+    pub const QUEEN_CASTLE: u16 = 3;
+    pub const CAPTURE: u16 = 4;
+    pub const EN_PASSANT: u16 = 5;
+    // the low two bits give the piece, N, B, R or Q, CAPTURE may be added
+    pub const PROMOTION: u16 = 8;
+
+    pub fn new(from: Square, to: Square, flags: u16) -> Self {
+        debug_assert!(flags < 16);
+        PackedMove(from.index() as u16 | (to.index() as u16) << 6 | flags << 12)
+    }
+
+    pub fn from_square(self) -> Square {
+        Square::new((self.0 & 0x3f) as usize)
+    }
+
+    pub fn to_square(self) -> Square {
+        Square::new((self.0 >> 6 & 0x3f) as usize)
+    }
+
+    pub fn flags(self) -> u16 {
+        self.0 >> 12
+    }
+
+    pub fn is_capture(self) -> bool {
+        self.flags() & Self::CAPTURE != 0
+    }
+
+    pub fn is_en_passant(self) -> bool {
+        self.flags() == Self::EN_PASSANT
+    }
+
+    pub fn is_double_push(self) -> bool {
+        self.flags() == Self::DOUBLE_PUSH
+    }
+
+    pub fn is_castling(self) -> bool {
+        self.flags() == Self::KING_CASTLE || self.flags() == Self::QUEEN_CASTLE
+    }
+
+    pub fn promotion(self) -> Option<PieceType> {
+        if self.flags() & Self::PROMOTION == 0 {
+            return None;
+        }
+
+        Some(match self.flags() & 3 {
+            0 => PieceType::Knight,
+            1 => PieceType::Bishop,
+            2 => PieceType::Rook,
+            _ => PieceType::Queen,
+        })
+    }
+
+    // PROMOTION flags for the given piece, None unless it is N, B, R or Q
+    pub fn promotion_flags(piece: PieceType) -> Option<u16> {
+        let bits = match piece {
+            PieceType::Knight => 0,
+            PieceType::Bishop => 1,
+            PieceType::Rook => 2,
+            PieceType::Queen => 3,
+            PieceType::Pawn | PieceType::King => return None,
+        };
+        Some(Self::PROMOTION | bits)
+    }
+}
+
+// UCI notation, e.g. e2e4 or e7e8q, castling being the king move e1g1
+impl fmt::Display for PackedMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.from_square(), self.to_square())?;
+        if let Some(piece) = self.promotion() {
+            write!(f, "{}", char::from(piece).to_ascii_lowercase())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl From<PackedMove> for u16 {
+    fn from(m: PackedMove) -> u16 {
+        m.0
+    }
+}
+
+impl From<u16> for PackedMove {
+    fn from(bits: u16) -> Self {
+        PackedMove(bits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packed_fields() {
+        let sq = |s: &str| s.parse::<Square>().unwrap();
+
+        let flags = PackedMove::CAPTURE | PackedMove::promotion_flags(PieceType::Queen).unwrap();
+        let m = PackedMove::new(sq("e7"), sq("d8"), flags);
+        assert_eq!(sq("e7"), m.from_square());
+        assert_eq!(sq("d8"), m.to_square());
+        assert_eq!(15, m.flags());
+        assert!(m.is_capture());
+        assert!(!m.is_en_passant() && !m.is_castling() && !m.is_double_push());
+        assert_eq!(Some(PieceType::Queen), m.promotion());
+        assert_eq!(m, PackedMove::from(u16::from(m)));
+        assert_eq!("e7d8q", m.to_string());
+
+        let rich = Move::new(sq("e7").into(), sq("d8").into(), PieceType::Pawn);
+        assert_eq!("e7d8", rich.to_uci());
+        assert_eq!("e7d8q", rich.with_promotion(PieceType::Queen).to_uci());
+
+        for piece in [PieceType::Knight, PieceType::Bishop, PieceType::Rook] {
+            let m = PackedMove::new(
+                sq("e7"),
+                sq("e8"),
+                PackedMove::promotion_flags(piece).unwrap(),
+            );
+            assert_eq!(Some(piece), m.promotion());
+            assert!(!m.is_capture());
+        }
+
+        let m = PackedMove::new(sq("e5"), sq("d6"), PackedMove::EN_PASSANT);
+        assert!(m.is_en_passant() && m.is_capture());
+        assert_eq!(None, m.promotion());
+
+        let m = PackedMove::new(sq("e1"), sq("c1"), PackedMove::QUEEN_CASTLE);
+        assert!(m.is_castling());
+        let m = PackedMove::new(sq("e2"), sq("e4"), PackedMove::DOUBLE_PUSH);
+        assert!(m.is_double_push());
+    }
 }
@@ -0,0 +1,169 @@
+use std::ops::{Deref, DerefMut};
+
+use crate::engine::r#move::PackedMove;
+
+// the list also holds the pseudo-legal moves before they are filtered. With
+// the material from_fen accepts, a side has at most a queen (27 moves), two
+// rooks (14), two bishops (13), two knights (8), eight promoted queens and a
+// king (8 and 2 castlings): 9 * 27 + 2 * 14 + 2 * 13 + 2 * 8 + 10 = 323.
+// This holds as long as boards can only be built by Board::new and from_fen
+// and changed by legal moves, the bitboards being private to the crate
+pub const MAX_MOVES: usize = 323;
+
+// fixed capacity list of moves living on the stack, so that generating moves
+// does not allocate
+#[derive(Clone, Copy)]
+pub struct MoveList {
+    moves: [PackedMove; MAX_MOVES],
+    len: usize,
+}
+
+impl MoveList {
+    pub fn new() -> Self {
+        MoveList {
+            moves: [PackedMove::default(); MAX_MOVES],
+            len: 0,
+        }
+    }
+
+    pub fn push(&mut self, m: PackedMove) {
+        debug_assert!(self.len < MAX_MOVES, "more than {} moves", MAX_MOVES);
+        self.moves[self.len] = m;
+        self.len += 1;
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    // keeps the moves for which f is true, preserving their order
+    pub fn retain(&mut self, mut f: impl FnMut(&PackedMove) -> bool) {
+        let mut kept = 0;
+        for i in 0..self.len {
+            if f(&self.moves[i]) {
+                self.moves[kept] = self.moves[i];
+                kept += 1;
+            }
+        }
+        self.len = kept;
+    }
+}
+
+impl Default for MoveList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// slice methods (len, iter, sort_by, indexing...) only see the pushed moves
+impl Deref for MoveList {
+    type Target = [PackedMove];
+
+    fn deref(&self) -> &[PackedMove] {
+        &self.moves[..self.len]
+    }
+}
+
+impl DerefMut for MoveList {
+    fn deref_mut(&mut self) -> &mut [PackedMove] {
+        &mut self.moves[..self.len]
+    }
+}
+
+impl std::fmt::Debug for MoveList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'a> IntoIterator for &'a MoveList {
+    type Item = &'a PackedMove;
+    type IntoIter = std::slice::Iter<'a, PackedMove>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct IntoIter {
+    list: MoveList,
+    index: usize,
+}
+
+impl Iterator for IntoIter {
+    type Item = PackedMove;
+
+    fn next(&mut self) -> Option<PackedMove> {
+        let m = self.list.get(self.index).copied();
+        self.index += 1;
+        m
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.list.len().saturating_sub(self.index);
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for IntoIter {}
+
+impl IntoIterator for MoveList {
+    type Item = PackedMove;
+    type IntoIter = IntoIter;
+
+    fn into_iter(self) -> IntoIter {
+        IntoIter {
+            list: self,
+            index: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::board::Board;
+    use crate::engine::square::Square;
+
+    #[test]
+    fn push_iterate_sort() {
+        let mut list = MoveList::new();
+        assert!(list.is_empty());
+
+        let push = |from| PackedMove::new(Square::new(from), Square::new(from + 8), 0);
+        for sq in [12, 8, 10] {
+            list.push(push(sq));
+        }
+        assert_eq!(3, list.len());
+        assert_eq!(push(8), list[1]);
+
+        list.sort_by_key(|m| m.from_square());
+        let from: Vec<_> = list.iter().map(|m| m.from_square().index()).collect();
+        assert_eq!(vec![8, 10, 12], from);
+
+        list.retain(|m| m.from_square().index() != 10);
+        assert_eq!(2, list.into_iter().len());
+
+        list.clear();
+        assert!(list.is_empty());
+    }
+
+    #[test]
+    fn legal_moves_fit() {
+        // position with the highest known number of legal moves
+        let board =
+            Board::from_fen("R6R/3Q4/1Q4Q1/4Q3/2Q4Q/Q4Q2/pp1Q4/kBNN1KB1 w - - 0 1").unwrap();
+        assert_eq!(218, board.generate_legal_moves().len());
+    }
+
+    #[test]
+    fn too_many_pieces_rejected() {
+        // would generate more than MAX_MOVES pseudo-legal moves
+        let fen = "1QQQQQ2/Q4QQQ/Q6R/Q6Q/Q3Q2Q/Q6Q/Q2Q3Q/kQRQ1QQK w - - 0 1";
+        assert!(Board::from_fen(fen).is_err());
+
+        // nine queens, in the limits of the material a game can reach
+        let board = Board::from_fen("kb6/pp6/8/Q1Q1Q1Q1/8/1Q1Q1Q1Q/8/QRBNKNBR w - - 0 1").unwrap();
+        assert!(board.generate_legal_moves().len() <= MAX_MOVES);
+    }
+}
@@ -0,0 +1,220 @@
+use crate::engine::bitboard::Bitboard;
+use crate::engine::board::Board;
+use crate::engine::piece::{Color, PieceType};
+
+const LIGHT_SQUARES: Bitboard = Bitboard(0x55aa55aa55aa55aa);
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum DrawReason {
+    Stalemate,
+    InsufficientMaterial,
+    // automatic draws
+    FivefoldRepetition,
+    SeventyFiveMoves,
+    // draws a player has to claim
+    ThreefoldRepetition,
+    FiftyMoves,
+}
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Outcome {
+    Win(Color),
+    Draw(DrawReason),
+}
+
+impl Board {
+    // number of times the current position occurred, itself included
+    pub fn repetition_count(&self) -> usize {
+        // positions before the last capture or pawn move cannot come back,
+        // and only those with the same side to move can repeat this one
+        let reversible = (self.halfmove_clock as usize).min(self.history.len());
+        let count = self.history[self.history.len() - reversible..]
+            .iter()
+            .rev()
+            .skip(1)
+            .step_by(2)
+            .filter(|hash| **hash == self.hash)
+            .count();
+
+        count + 1
+    }
+
+    pub fn is_threefold_repetition(&self) -> bool {
+        self.repetition_count() >= 3
+    }
+
+    pub fn is_fivefold_repetition(&self) -> bool {
+        self.repetition_count() >= 5
+    }
+
+    pub fn is_fifty_moves(&self) -> bool {
+        self.halfmove_clock >= 100
+    }
+
+    pub fn is_seventy_five_moves(&self) -> bool {
+        self.halfmove_clock >= 150
+    }
+
+    // neither side can possibly mate: lone kings, a single minor piece, or
+    // bishops all moving on squares of the same color
+    pub fn is_insufficient_material(&self) -> bool {
+        let both = |piece| self.pieces(piece, Color::White) | self.pieces(piece, Color::Black);
+
+        if !(both(PieceType::Pawn) | both(PieceType::Rook) | both(PieceType::Queen)).is_empty() {
+            return false;
+        }
+
+        let knights = both(PieceType::Knight);
+        let bishops = both(PieceType::Bishop);
+        if !knights.is_empty() {
+            return (knights | bishops).popcount() <= 1;
+        }
+
+        (bishops & LIGHT_SQUARES).is_empty() || (bishops & !LIGHT_SQUARES).is_empty()
+    }
+
+    // draw the side to move may claim, if any
+    pub fn can_claim_draw(&self) -> Option<DrawReason> {
+        if self.is_threefold_repetition() {
+            Some(DrawReason::ThreefoldRepetition)
+        } else if self.is_fifty_moves() {
+            Some(DrawReason::FiftyMoves)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::r#move::Move;
+    use crate::engine::square::Square;
+
+    fn play(board: &mut Board, moves: &[(usize, usize)]) {
+        for (from, to) in moves {
+            let piece_type = board[Square::new(*from)].unwrap().0;
+            board.do_move(&Move::new(
+                Square::new(*from).into(),
+                Square::new(*to).into(),
+                piece_type,
+            ));
+        }
+    }
+
+    // Nf3 Nf6 Ng1 Ng8
+    const KNIGHT_SHUFFLE: [(usize, usize); 4] = [(6, 21), (62, 45), (21, 6), (45, 62)];
+
+    #[test]
+    fn repetitions() {
+        let mut board = Board::new();
+        assert_eq!(1, board.repetition_count());
+
+        play(&mut board, &KNIGHT_SHUFFLE);
+        assert_eq!(2, board.repetition_count());
+        assert_eq!(None, board.can_claim_draw());
+
+        play(&mut board, &KNIGHT_SHUFFLE);
+        assert!(board.is_threefold_repetition());
+        assert_eq!(
+            Some(DrawReason::ThreefoldRepetition),
+            board.can_claim_draw()
+        );
+        assert_eq!(None, board.outcome());
+
+        play(&mut board, &KNIGHT_SHUFFLE);
+        play(&mut board, &KNIGHT_SHUFFLE);
+        assert!(board.is_fivefold_repetition());
+        assert_eq!(
+            Some(Outcome::Draw(DrawReason::FivefoldRepetition)),
+            board.outcome()
+        );
+    }
+
+    #[test]
+    fn repetitions_reset_by_pawn_moves() {
+        let mut board = Board::new();
+        play(&mut board, &KNIGHT_SHUFFLE);
+        play(&mut board, &[(12, 20), (52, 44)]); // e3 e6
+        play(&mut board, &KNIGHT_SHUFFLE);
+        assert_eq!(2, board.repetition_count());
+    }
+
+    #[test]
+    fn halfmove_clock() {
+        let mut board = Board::new();
+        play(&mut board, &[(6, 21), (62, 45)]); // Nf3 Nf6
+        assert_eq!(2, board.halfmove_clock());
+        play(&mut board, &[(12, 28)]); // e4
+        assert_eq!(0, board.halfmove_clock());
+        play(&mut board, &[(45, 28)]); // Nxe4
+        assert_eq!(0, board.halfmove_clock());
+        play(&mut board, &[(5, 12)]); // Be2
+        assert_eq!(1, board.halfmove_clock());
+
+        let bb5 = board.pack_move(&Move::new(
+            Square::new(12).into(),
+            Square::new(33).into(),
+            PieceType::Bishop,
+        ));
+        let undo = board.make_move(bb5);
+        assert_eq!(2, board.halfmove_clock());
+        board.unmake_move(bb5, undo);
+        assert_eq!(1, board.halfmove_clock());
+    }
+
+    #[test]
+    fn move_rules() {
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 99 80").unwrap();
+        assert_eq!(None, board.can_claim_draw());
+
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 100 80").unwrap();
+        assert_eq!(Some(DrawReason::FiftyMoves), board.can_claim_draw());
+        assert_eq!(None, board.outcome());
+
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 150 80").unwrap();
+        assert_eq!(
+            Some(Outcome::Draw(DrawReason::SeventyFiveMoves)),
+            board.outcome()
+        );
+
+        // mate takes precedence over the seventy-five move rule
+        let board = Board::from_fen("R3k3/8/4K3/8/8/8/8/8 b - - 150 80").unwrap();
+        assert_eq!(Some(Outcome::Win(Color::White)), board.outcome());
+    }
+
+    #[test]
+    fn insufficient_material() {
+        let insufficient = [
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4KN2 w - - 0 1",
+            "4kb2/8/8/8/8/8/8/4K3 w - - 0 1",
+            // all bishops on dark squares
+            "4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/B1B1K3 w - - 0 1",
+        ];
+        let sufficient = [
+            "4k3/8/8/8/8/8/8/4KNN1 w - - 0 1",
+            "4kn2/8/8/8/8/8/8/4KB2 w - - 0 1",
+            "4kb2/8/8/8/8/8/8/3BK3 w - - 0 1",
+            "4k3/8/8/8/8/8/P7/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4K2R w - - 0 1",
+        ];
+
+        for fen in insufficient {
+            let board = Board::from_fen(fen).unwrap();
+            assert!(board.is_insufficient_material(), "{}", fen);
+            assert_eq!(
+                Some(Outcome::Draw(DrawReason::InsufficientMaterial)),
+                board.outcome()
+            );
+        }
+        for fen in sufficient {
+            assert!(
+                !Board::from_fen(fen).unwrap().is_insufficient_material(),
+                "{}",
+                fen
+            );
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use std::ops::AddAssign;
+
+use crate::engine::board::Board;
+use crate::engine::r#move::PackedMove;
+
+// breakdown of the leaf nodes of a perft, as in the published perft tables
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Default)]
+pub struct PerftStats {
+    pub nodes: u64,
+    pub captures: u64,
+    pub en_passants: u64,
+    pub castles: u64,
+    pub promotions: u64,
+    pub checks: u64,
+    // single checks given by a piece other than the one that moved, double
+    // checks are only counted as such
+    pub discovered_checks: u64,
+    pub double_checks: u64,
+    pub checkmates: u64,
+}
+
+impl AddAssign for PerftStats {
+    fn add_assign(&mut self, other: Self) {
+        self.nodes += other.nodes;
+        self.captures += other.captures;
+        self.en_passants += other.en_passants;
+        self.castles += other.castles;
+        self.promotions += other.promotions;
+        self.checks += other.checks;
+        self.discovered_checks += other.discovered_checks;
+        self.double_checks += other.double_checks;
+        self.checkmates += other.checkmates;
+    }
+}
+
+impl Board {
+    // number of leaf nodes `depth` plies below this position, the moves of the
+    // last ply being counted rather than played
+    pub fn perft(&mut self, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+
+        let moves = self.generate_legal_moves();
+        if depth == 1 {
+            return moves.len() as u64;
+        }
+
+        let mut nodes = 0;
+        for m in moves {
+            let undo = self.make_move(m);
+            nodes += self.perft(depth - 1);
+            self.unmake_move(m, undo);
+        }
+
+        nodes
+    }
+
+    // like perft, but every leaf move is played to classify it
+    pub fn perft_stats(&mut self, depth: u32) -> PerftStats {
+        let mut stats = PerftStats::default();
+        if depth == 0 {
+            stats.nodes = 1;
+            return stats;
+        }
+
+        for m in self.generate_legal_moves() {
+            if depth == 1 {
+                self.count_leaf(m, &mut stats);
+            } else {
+                let undo = self.make_move(m);
+                stats += self.perft_stats(depth - 1);
+                self.unmake_move(m, undo);
+            }
+        }
+
+        stats
+    }
+
+    fn count_leaf(&mut self, m: PackedMove, stats: &mut PerftStats) {
+        stats.nodes += 1;
+        stats.captures += m.is_capture() as u64;
+        stats.en_passants += m.is_en_passant() as u64;
+        stats.castles += m.is_castling() as u64;
+        stats.promotions += m.promotion().is_some() as u64;
+
+        // squares of the pieces the move itself brought into place
+        let mut moved = m.to_square().bb();
+        if let Some((_, rook)) = Board::castling_rook_squares(m) {
+            moved |= rook.bb();
+        }
+
+        let undo = self.make_move(m);
+        let checkers = self.checkers();
+        if !checkers.is_empty() {
+            stats.checks += 1;
+            if checkers.popcount() > 1 {
+                stats.double_checks += 1;
+            } else if !(checkers & !moved).is_empty() {
+                stats.discovered_checks += 1;
+            }
+            stats.checkmates += self.generate_legal_moves().is_empty() as u64;
+        }
+        self.unmake_move(m, undo);
+    }
+
+    // perft of every root move, whose Display is its UCI notation
+    pub fn perft_divide(&mut self, depth: u32) -> Vec<(PackedMove, u64)> {
+        if depth == 0 {
+            return Vec::new();
+        }
+
+        self.generate_legal_moves()
+            .into_iter()
+            .map(|m| {
+                let undo = self.make_move(m);
+                let nodes = self.perft(depth - 1);
+                self.unmake_move(m, undo);
+                (m, nodes)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn divide() {
+        let mut board = Board::new();
+        assert_eq!(1, board.perft(0));
+        assert!(board.perft_divide(0).is_empty());
+
+        let divide = board.perft_divide(3);
+        assert_eq!(20, divide.len());
+        assert_eq!(8902, divide.iter().map(|(_, n)| n).sum::<u64>());
+
+        let nodes = |uci| divide.iter().find(|(m, _)| m.to_string() == uci).unwrap().1;
+        assert_eq!(600, nodes("e2e4"));
+        assert_eq!(440, nodes("g1f3"));
+        assert_eq!(380, nodes("a2a3"));
+        assert!(board == Board::new());
+    }
+
+    #[test]
+    fn divide_uci_moves() {
+        let mut board =
+            Board::from_fen("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1")
+                .unwrap();
+        let mut divide: Vec<_> = board
+            .perft_divide(2)
+            .into_iter()
+            .map(|(m, n)| (m.to_string(), n))
+            .collect();
+        divide.sort();
+
+        let expected = [
+            ("b4c5", 42),
+            ("c4c5", 43),
+            ("d2d4", 43),
+            ("f1f2", 45),
+            ("f3d4", 45),
+            ("g1h1", 46),
+        ];
+        assert_eq!(expected.map(|(m, n)| (m.to_string(), n)).to_vec(), divide);
+
+        let mut board = Board::from_fen("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let divide = board.perft_divide(1);
+        let promotions: Vec<_> = divide
+            .iter()
+            .map(|(m, _)| m.to_string())
+            .filter(|m| m.starts_with("b7"))
+            .collect();
+        assert_eq!(vec!["b7b8q", "b7b8r", "b7b8b", "b7b8n"], promotions);
+    }
+}
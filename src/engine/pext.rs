@@ -0,0 +1,104 @@
+use std::arch::x86_64::_pext_u64;
+use std::sync::OnceLock;
+
+use crate::engine::magic::relevant_blockers;
+use crate::engine::rules_bb::{bishop_attacks_slow, rook_attacks_slow};
+
+// BMI2 replacement for the magic tables: pext packs the relevant blockers
+// into a dense index, so no magic multiplication nor shift is needed.
+// Tables only exist when the CPU supports BMI2, callers fall back to the
+// magic tables otherwise.
+pub(crate) struct PextTable {
+    masks: [u64; 64],
+    offsets: [usize; 64],
+    attacks: Vec<u64>,
+}
+
+impl PextTable {
+    #[target_feature(enable = "bmi2")]
+    unsafe fn new(slow_attacks: fn(usize, u64) -> u64) -> Self {
+        let mut table = PextTable {
+            masks: [0; 64],
+            offsets: [0; 64],
+            attacks: Vec::new(),
+        };
+
+        for sq in 0..64 {
+            let mask = relevant_blockers(sq, slow_attacks);
+            table.masks[sq] = mask;
+            table.offsets[sq] = table.attacks.len();
+            table
+                .attacks
+                .resize(table.offsets[sq] + (1 << mask.count_ones()), 0);
+
+            let mut blockers = 0u64;
+            loop {
+                let index = table.offsets[sq] + _pext_u64(blockers, mask) as usize;
+                table.attacks[index] = slow_attacks(sq, blockers);
+                blockers = blockers.wrapping_sub(mask) & mask;
+                if blockers == 0 {
+                    break;
+                }
+            }
+        }
+
+        table
+    }
+
+    #[target_feature(enable = "bmi2")]
+    unsafe fn attacks_bmi2(&self, sq: usize, blockers: u64) -> u64 {
+        self.attacks[self.offsets[sq] + _pext_u64(blockers, self.masks[sq]) as usize]
+    }
+
+    pub(crate) fn attacks(&self, sq: usize, blockers: u64) -> u64 {
+        // SAFETY: a table is only built once BMI2 support has been detected
+        unsafe { self.attacks_bmi2(sq, blockers) }
+    }
+}
+
+fn build(slow_attacks: fn(usize, u64) -> u64) -> Option<PextTable> {
+    if is_x86_feature_detected!("bmi2") {
+        // SAFETY: BMI2 support was just checked
+        Some(unsafe { PextTable::new(slow_attacks) })
+    } else {
+        None
+    }
+}
+
+pub(crate) fn rook_table() -> Option<&'static PextTable> {
+    static TABLE: OnceLock<Option<PextTable>> = OnceLock::new();
+    TABLE.get_or_init(|| build(rook_attacks_slow)).as_ref()
+}
+
+pub(crate) fn bishop_table() -> Option<&'static PextTable> {
+    static TABLE: OnceLock<Option<PextTable>> = OnceLock::new();
+    TABLE.get_or_init(|| build(bishop_attacks_slow)).as_ref()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::board::tests::Rng;
+    use crate::engine::magic;
+
+    #[test]
+    fn pext_matches_magic() {
+        let (Some(rooks), Some(bishops)) = (rook_table(), bishop_table()) else {
+            return; // no BMI2 on this CPU, nothing to compare
+        };
+
+        let mut rng = Rng(0x5851f42d4c957f2d);
+        for _ in 0..100_000 {
+            let sq = rng.next_u64() as usize % 64;
+            let blockers = rng.next_u64() & rng.next_u64();
+            assert_eq!(
+                magic::rook_table().attacks(sq, blockers),
+                rooks.attacks(sq, blockers)
+            );
+            assert_eq!(
+                magic::bishop_table().attacks(sq, blockers),
+                bishops.attacks(sq, blockers)
+            );
+        }
+    }
+}
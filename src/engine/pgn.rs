@@ -0,0 +1,937 @@
+use std::fmt;
+use std::io::{self, BufRead};
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::engine::board::{Board, Undo};
+use crate::engine::fen::FenError;
+use crate::engine::game::{Game, MoveNode, SEVEN_TAG_ROSTER};
+use crate::engine::piece::Color;
+use crate::engine::r#move::PackedMove;
+use crate::engine::san::SanError;
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum PgnErrorKind {
+    Io(io::ErrorKind),
+    InvalidTag,
+    UnterminatedComment,
+    UnexpectedChar(char),
+    InvalidNag(String),
+    // a move after the game termination marker
+    UnexpectedMove(String),
+    // a variation before any move it could replace
+    MisplacedVariation,
+    UnmatchedVariationEnd,
+    UnterminatedVariation,
+    Fen(FenError),
+    San(SanError),
+}
+
+impl fmt::Display for PgnErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PgnErrorKind::Io(kind) => write!(f, "read error: {}", kind),
+            PgnErrorKind::InvalidTag => write!(f, "invalid tag pair"),
+            PgnErrorKind::UnterminatedComment => write!(f, "unterminated comment"),
+            PgnErrorKind::UnexpectedChar(c) => write!(f, "unexpected character '{}'", c),
+            PgnErrorKind::InvalidNag(s) => write!(f, "invalid annotation glyph '{}'", s),
+            PgnErrorKind::UnexpectedMove(s) => write!(f, "move '{}' after the result", s),
+            PgnErrorKind::MisplacedVariation => write!(f, "variation before any move"),
+            PgnErrorKind::UnmatchedVariationEnd => write!(f, "')' closes no variation"),
+            PgnErrorKind::UnterminatedVariation => write!(f, "unterminated variation"),
+            PgnErrorKind::Fen(e) => write!(f, "invalid FEN tag: {}", e),
+            PgnErrorKind::San(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct PgnError {
+    // 1 based, as shown by editors
+    pub line: usize,
+    pub kind: PgnErrorKind,
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.kind)
+    }
+}
+
+impl std::error::Error for PgnError {}
+
+// maximum line length of the export format
+const PGN_LINE_LENGTH: usize = 80;
+
+#[derive(Clone, Debug)]
+enum Token {
+    Tag(String, String),
+    Comment(String),
+    Nag(u8),
+    VariationStart,
+    VariationEnd,
+    Move(String),
+    Termination(String),
+}
+
+// glyphs written as move suffixes, standing for $1 to $6
+const SUFFIX_NAGS: [&str; 6] = ["!", "?", "!!", "??", "!?", "?!"];
+
+struct Lexer<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+    // suffix glyph of the move just returned
+    pending: Option<(usize, Token)>,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(text: &'a str, line: usize) -> Self {
+        Lexer {
+            chars: text.chars().peekable(),
+            line,
+            pending: None,
+        }
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if c == Some('\n') {
+            self.line += 1;
+        }
+        c
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.peek().is_some_and(|c| c.is_whitespace()) {
+            self.bump();
+        }
+    }
+
+    // [Name "value"], the opening bracket being consumed
+    fn tag(&mut self) -> Option<Token> {
+        self.skip_whitespace();
+        let mut name = String::new();
+        while let Some(c) = self
+            .chars
+            .next_if(|c| c.is_ascii_alphanumeric() || *c == '_')
+        {
+            name.push(c);
+        }
+        self.skip_whitespace();
+        if name.is_empty() || self.bump()? != '"' {
+            return None;
+        }
+
+        let mut value = String::new();
+        loop {
+            match self.bump()? {
+                '"' => break,
+                '\\' => value.push(self.bump()?),
+                '\n' => return None,
+                c => value.push(c),
+            }
+        }
+        self.skip_whitespace();
+
+        (self.bump()? == ']').then_some(Token::Tag(name, value))
+    }
+
+    fn is_symbol_char(c: char) -> bool {
+        c.is_ascii_alphanumeric() || "+#=:-/!?*_".contains(c)
+    }
+}
+
+impl Iterator for Lexer<'_> {
+    type Item = Result<(usize, Token), PgnError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(pending) = self.pending.take() {
+            return Some(Ok(pending));
+        }
+
+        loop {
+            self.skip_whitespace();
+            let line = self.line;
+            let error = |kind| Some(Err(PgnError { line, kind }));
+            let token = match self.bump()? {
+                '[' => match self.tag() {
+                    Some(tag) => tag,
+                    None => return error(PgnErrorKind::InvalidTag),
+                },
+                '{' => {
+                    let mut comment = String::new();
+                    loop {
+                        match self.bump() {
+                            Some('}') => break,
+                            Some(c) => comment.push(c),
+                            None => return error(PgnErrorKind::UnterminatedComment),
+                        }
+                    }
+                    Token::Comment(comment)
+                }
+                ';' => {
+                    let mut comment = String::new();
+                    while let Some(c) = self.chars.next_if(|c| *c != '\n') {
+                        comment.push(c);
+                    }
+                    Token::Comment(comment)
+                }
+                '$' => {
+                    let mut digits = String::new();
+                    while let Some(c) = self.chars.next_if(char::is_ascii_digit) {
+                        digits.push(c);
+                    }
+                    match digits.parse() {
+                        Ok(nag) => Token::Nag(nag),
+                        Err(_) => return error(PgnErrorKind::InvalidNag(format!("${}", digits))),
+                    }
+                }
+                '(' => Token::VariationStart,
+                ')' => Token::VariationEnd,
+                // dots of move numbers, 12. or 12...
+                '.' => continue,
+                c if Self::is_symbol_char(c) => {
+                    let mut symbol = String::from(c);
+                    while let Some(c) = self.chars.next_if(|c| Self::is_symbol_char(*c)) {
+                        symbol.push(c);
+                    }
+
+                    let suffix = symbol.trim_start_matches(|c| c != '!' && c != '?');
+                    let nag = match suffix {
+                        "" => None,
+                        _ => match SUFFIX_NAGS.iter().position(|s| *s == suffix) {
+                            Some(i) => Some(Token::Nag(i as u8 + 1)),
+                            None => return error(PgnErrorKind::InvalidNag(suffix.to_string())),
+                        },
+                    };
+                    let symbol = &symbol[..symbol.len() - suffix.len()];
+
+                    match symbol {
+                        "1-0" | "0-1" | "1/2-1/2" | "*" => Token::Termination(symbol.to_string()),
+                        // suffix written apart from its move
+                        "" => nag.unwrap(),
+                        s if s.chars().all(|c| c.is_ascii_digit()) => continue,
+                        _ => {
+                            self.pending = nag.map(|nag| (line, nag));
+                            Token::Move(symbol.to_string())
+                        }
+                    }
+                }
+                c => return error(PgnErrorKind::UnexpectedChar(c)),
+            };
+
+            return Some(Ok((line, token)));
+        }
+    }
+}
+
+// line being read, and the position after its moves
+struct Frame {
+    nodes: Vec<MoveNode>,
+    board: Board,
+    // to go back to the position before the last move, where its variations
+    // start
+    last: Option<(PackedMove, Undo)>,
+    comment_before: Option<String>,
+    // where the variation opens, for errors
+    line: usize,
+}
+
+impl Frame {
+    fn new(board: Board, line: usize) -> Self {
+        Frame {
+            nodes: Vec::new(),
+            board,
+            last: None,
+            comment_before: None,
+            line,
+        }
+    }
+}
+
+// parses the text of a single game, starting at line `line` of the file
+fn parse_game(text: &str, line: usize) -> Result<Game, PgnError> {
+    let mut game = Game::new();
+    // the mainline and the variations opened from it
+    let mut frames: Vec<Frame> = Vec::new();
+    let mut result = None;
+
+    for token in Lexer::new(text, line) {
+        let (line, token) = token?;
+        let error = |kind| PgnError { line, kind };
+
+        if let Token::Tag(name, value) = token {
+            game.tags.push((name, value));
+            continue;
+        }
+        if frames.is_empty() {
+            let board = game
+                .starting_board()
+                .map_err(|e| error(PgnErrorKind::Fen(e)))?;
+            frames.push(Frame::new(board, line));
+        }
+        let depth = frames.len();
+        let frame = frames.last_mut().unwrap();
+
+        match token {
+            Token::Tag(..) => unreachable!(),
+            Token::Move(san) if result.is_some() && depth == 1 => {
+                return Err(error(PgnErrorKind::UnexpectedMove(san)))
+            }
+            Token::Move(san) => {
+                let m = frame
+                    .board
+                    .parse_san(&san)
+                    .map_err(|e| error(PgnErrorKind::San(e)))?;
+                let packed = frame.board.pack_move(&m);
+                let undo = frame.board.make_move(packed);
+                frame.last = Some((packed, undo));
+
+                let mut node = MoveNode::new(m);
+                node.comment_before = frame.comment_before.take();
+                frame.nodes.push(node);
+            }
+            Token::Comment(text) => match frame.nodes.last_mut() {
+                Some(node) => node.add_comment(&text),
+                None => {
+                    let text = text.trim();
+                    frame.comment_before = match frame.comment_before.take() {
+                        Some(comment) => Some(comment + " " + text),
+                        None => Some(text.to_string()),
+                    };
+                }
+            },
+            Token::Nag(nag) => {
+                if let Some(node) = frame.nodes.last_mut() {
+                    node.nags.push(nag);
+                }
+            }
+            Token::VariationStart => {
+                let (packed, undo) = frame
+                    .last
+                    .ok_or_else(|| error(PgnErrorKind::MisplacedVariation))?;
+                let mut board = frame.board.clone();
+                board.unmake_move(packed, undo);
+                frames.push(Frame::new(board, line));
+            }
+            Token::VariationEnd => {
+                if depth == 1 {
+                    return Err(error(PgnErrorKind::UnmatchedVariationEnd));
+                }
+                let variation = frames.pop().unwrap().nodes;
+                let parent = frames.last_mut().unwrap();
+                if !variation.is_empty() {
+                    parent.nodes.last_mut().unwrap().variations.push(variation);
+                }
+            }
+            Token::Termination(marker) if depth == 1 => result = Some(marker),
+            Token::Termination(_) => {}
+        }
+    }
+
+    if frames.len() > 1 {
+        return Err(PgnError {
+            line: frames.last().unwrap().line,
+            kind: PgnErrorKind::UnterminatedVariation,
+        });
+    }
+    if let Some(frame) = frames.pop() {
+        game.mainline = frame.nodes;
+    }
+    if let (None, Some(result)) = (game.tag("Result"), result) {
+        game.set_tag("Result", &result);
+    }
+
+    Ok(game)
+}
+
+// reads the games of a PGN file one at a time, a malformed game being
+// reported without stopping the reading of the next ones
+pub struct PgnReader<R> {
+    reader: R,
+    // number of lines read so far
+    line: usize,
+    // first line of the next game and its number, read while looking for the
+    // end of the previous one
+    pending: Option<(usize, String)>,
+    done: bool,
+}
+
+impl<R: BufRead> PgnReader<R> {
+    pub fn new(reader: R) -> Self {
+        PgnReader {
+            reader,
+            line: 0,
+            pending: None,
+            done: false,
+        }
+    }
+
+    fn read_line(&mut self) -> io::Result<Option<(usize, String)>> {
+        if let Some(pending) = self.pending.take() {
+            return Ok(Some(pending));
+        }
+
+        // files are often in Latin-1, whose accented names are not worth
+        // losing the rest of the file for
+        let mut bytes = Vec::new();
+        if self.reader.read_until(b'\n', &mut bytes)? == 0 {
+            return Ok(None);
+        }
+        self.line += 1;
+        Ok(Some((
+            self.line,
+            String::from_utf8_lossy(&bytes).into_owned(),
+        )))
+    }
+
+    // text of the next game and its first line, a game ending at its
+    // termination marker or where a tag pair follows movetext
+    fn read_game(&mut self) -> io::Result<Option<(String, usize)>> {
+        let mut text = String::new();
+        let mut first_line = None;
+        let mut in_movetext = false;
+        let mut in_comment = false;
+        let mut has_tags = false;
+        let mut depth = 0usize;
+
+        while let Some((number, mut line)) = self.read_line()? {
+            // escaped lines are meant for other tools
+            if line.starts_with('%') {
+                continue;
+            }
+            if !in_comment && line.trim_start().starts_with('[') {
+                if in_movetext {
+                    self.pending = Some((number, line));
+                    break;
+                }
+                has_tags = true;
+            } else if let Some(end) =
+                Self::scan_movetext(&line, &mut in_comment, &mut in_movetext, &mut depth)
+            {
+                // what follows the marker belongs to the next game
+                let rest = line.split_off(end);
+                if !rest.trim().is_empty() {
+                    self.pending = Some((number, rest));
+                }
+                if first_line.is_none() {
+                    first_line = Some(number);
+                }
+                text.push_str(&line);
+                break;
+            }
+
+            if first_line.is_none() && !line.trim().is_empty() {
+                first_line = Some(number);
+            }
+            if first_line.is_some() {
+                text.push_str(&line);
+            }
+        }
+
+        // a comment left after the last marker is no game
+        if !in_movetext && !has_tags {
+            return Ok(None);
+        }
+
+        Ok(first_line.map(|line| (text, line)))
+    }
+
+    // follows comments and variations along a line of movetext, returning
+    // where a termination marker outside of them ends
+    fn scan_movetext(
+        line: &str,
+        in_comment: &mut bool,
+        in_movetext: &mut bool,
+        depth: &mut usize,
+    ) -> Option<usize> {
+        let mut symbol = String::new();
+
+        for (i, c) in line.char_indices().chain([(line.len(), ' ')]) {
+            if *in_comment {
+                *in_comment = c != '}';
+                continue;
+            }
+            if Lexer::is_symbol_char(c) {
+                symbol.push(c);
+                *in_movetext = true;
+                continue;
+            }
+            if *depth == 0 && matches!(symbol.as_str(), "1-0" | "0-1" | "1/2-1/2" | "*") {
+                return Some(i);
+            }
+            symbol.clear();
+
+            match c {
+                '{' => *in_comment = true,
+                // the rest of the line is a comment
+                ';' => break,
+                '(' => *depth += 1,
+                ')' => *depth = depth.saturating_sub(1),
+                c if !c.is_whitespace() => *in_movetext = true,
+                _ => {}
+            }
+        }
+
+        None
+    }
+}
+
+impl<R: BufRead> Iterator for PgnReader<R> {
+    type Item = Result<Game, PgnError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        match self.read_game() {
+            Ok(Some((text, line))) => Some(parse_game(&text, line)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(PgnError {
+                    line: self.line + 1,
+                    kind: PgnErrorKind::Io(e.kind()),
+                }))
+            }
+        }
+    }
+}
+
+fn write_tag(pgn: &mut String, name: &str, value: &str) {
+    // a tag pair must hold on a single line
+    let value = value
+        .replace(['\n', '\r'], " ")
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"");
+    pgn.push_str(&format!("[{} \"{}\"]\n", name, value));
+}
+
+// words of a comment, so that long ones can be wrapped. Braces cannot be
+// escaped in a comment, so closing ones are left out
+fn push_comment(tokens: &mut Vec<String>, comment: &str) {
+    let mut words: Vec<String> = comment
+        .replace('}', "")
+        .split_whitespace()
+        .map(String::from)
+        .collect();
+    match words.len() {
+        0 => words.push(String::from("{}")),
+        len => {
+            words[0].insert(0, '{');
+            words[len - 1].push('}');
+        }
+    }
+    tokens.extend(words);
+}
+
+// movetext of `nodes` played from `board`, which must all be legal
+fn push_line(
+    board: &Board,
+    nodes: &[MoveNode],
+    tokens: &mut Vec<String>,
+) -> Result<(), PgnErrorKind> {
+    let mut board = board.clone();
+    // black moves get their number at the start of a line or after a break
+    let mut needs_number = true;
+
+    for node in nodes {
+        if let Some(comment) = &node.comment_before {
+            push_comment(tokens, comment);
+            needs_number = true;
+        }
+
+        if !board.is_move_legal(&node.m) {
+            let uci = node.m.to_uci();
+            return Err(PgnErrorKind::San(SanError::IllegalMove(uci)));
+        }
+
+        // numbers stay on the line of their move
+        let number = board.fullmove_number();
+        let san = board.to_san(&node.m);
+        tokens.push(match board.side_to_move {
+            Color::White => format!("{}. {}", number, san),
+            Color::Black if needs_number => format!("{}... {}", number, san),
+            Color::Black => san,
+        });
+        needs_number = false;
+
+        tokens.extend(node.nags.iter().map(|nag| format!("${}", nag)));
+        if let Some(comment) = node.full_comment() {
+            push_comment(tokens, &comment);
+            needs_number = true;
+        }
+
+        for variation in node.variations.iter().filter(|v| !v.is_empty()) {
+            let start = tokens.len();
+            push_line(&board, variation, tokens)?;
+            tokens[start].insert(0, '(');
+            tokens.last_mut().unwrap().push(')');
+            needs_number = true;
+        }
+
+        board.do_move(&node.m);
+    }
+
+    Ok(())
+}
+
+impl Game {
+    // export format: the seven tag roster then the other tags, and the moves
+    // in SAN on lines of at most 80 columns. Fails on a bad FEN tag or on a
+    // move that is not legal where it is played
+    pub fn to_pgn(&self) -> Result<String, PgnErrorKind> {
+        let mut pgn = String::new();
+        for (name, unknown) in SEVEN_TAG_ROSTER {
+            write_tag(&mut pgn, name, self.tag(name).unwrap_or(unknown));
+        }
+        for (name, value) in &self.tags {
+            if !SEVEN_TAG_ROSTER.iter().any(|(roster, _)| roster == name) {
+                write_tag(&mut pgn, name, value);
+            }
+        }
+        pgn.push('\n');
+
+        let mut tokens = Vec::new();
+        let board = self.starting_board().map_err(PgnErrorKind::Fen)?;
+        push_line(&board, &self.mainline, &mut tokens)?;
+        tokens.push(self.tag("Result").unwrap_or("*").to_string());
+
+        let mut line = String::new();
+        for token in tokens {
+            if !line.is_empty() && line.len() + 1 + token.len() > PGN_LINE_LENGTH {
+                pgn.push_str(&line);
+                pgn.push('\n');
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&token);
+        }
+        pgn.push_str(&line);
+        pgn.push('\n');
+
+        Ok(pgn)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::game::Eval;
+    use crate::engine::piece::PieceType;
+    use std::time::Duration;
+
+    const GAMES: &str = r#"[Event "F/S Return Match"]
+[Site "Belgrade, Serbia JUG"]
+[Date "1992.11.04"]
+[Round "29"]
+[White "Fischer, Robert J."]
+[Black "Spassky, Boris V."]
+[Result "1/2-1/2"]
+
+1. e4 e5 2. Nf3 Nc6 3. Bb5 {This opening is called the Ruy Lopez.} 3... a6
+4. Ba4 Nf6 5. O-O Be7 6. Re1 b5 7. Bb3 d6 8. c3 O-O 9. h3 Nb8 10. d4 Nbd7
+11. c4 c6 12. cxb5 axb5 13. Nc3 Bb7 14. Bg5 b4 15. Nb1 h6 16. Bh4 c5 17. dxe5
+Nxe4 18. Bxe7 Qxe7 19. exd6 Qf6 20. Nbd2 Nxd6 21. Nc4 Nxc4 22. Bxc4 Nb6
+23. Ne5 Rae8 24. Bxf7+ Rxf7 25. Nxf7 Rxe1+ 26. Qxe1 Kxf7 27. Qe3 Qg5 28. Qxg5
+hxg5 29. b3 Ke6 30. a3 Kd6 31. axb4 cxb4 32. Ra5 Nd5 33. f3 Bc8 34. Kf2 Bf5
+35. Ra7 g6 36. Ra6+ Kc5 37. Ke1 Nf4 38. g3 Nxh3 39. Kd2 Kb5 40. Rd6 Kc5 41. Ra6
+Nf2 42. g4 Bd3 43. Re6 1/2-1/2
+
+[Event "Variations"]
+[Result "*"]
+
+1. d4 $1 d5 (1... Nf6 2. c4 (2. Nf3 g6) e6) ; rest of line comment
+2. c4!? {a comment
+over two lines} e6 *
+"#;
+
+    fn read(pgn: &str) -> Vec<Result<Game, PgnError>> {
+        PgnReader::new(pgn.as_bytes()).collect()
+    }
+
+    #[test]
+    fn read_games() {
+        let games = read(GAMES);
+        assert_eq!(2, games.len());
+
+        let game = games[0].as_ref().unwrap();
+        assert_eq!(7, game.tags.len());
+        assert_eq!(Some("Fischer, Robert J."), game.tag("White"));
+        assert_eq!(Some("1/2-1/2"), game.tag("Result"));
+        assert_eq!(None, game.tag("FEN"));
+        assert_eq!(85, game.mainline.len());
+        assert_eq!(PieceType::King, game.mainline[8].m.piece_type);
+        assert_eq!("b1d2", game.mainline[38].m.to_uci());
+        assert_eq!(
+            Some("This opening is called the Ruy Lopez."),
+            game.mainline[4].comment.as_deref()
+        );
+
+        let game = games[1].as_ref().unwrap();
+        let uci =
+            |nodes: &[MoveNode]| -> Vec<String> { nodes.iter().map(|n| n.m.to_uci()).collect() };
+        assert_eq!(vec!["d2d4", "d7d5", "c2c4", "e7e6"], uci(&game.mainline));
+        assert_eq!(vec![1], game.mainline[0].nags);
+        assert_eq!(vec![5], game.mainline[2].nags);
+        assert_eq!(
+            Some("a comment over two lines"),
+            game.mainline[2].comment.as_deref()
+        );
+        assert_eq!(
+            Some("rest of line comment"),
+            game.mainline[1].comment.as_deref()
+        );
+
+        let variation = &game.mainline[1].variations[0];
+        assert_eq!(vec!["g8f6", "c2c4", "e7e6"], uci(variation));
+        assert_eq!(vec!["g1f3", "g7g6"], uci(&variation[1].variations[0]));
+    }
+
+    #[test]
+    fn setup_and_escapes() {
+        let pgn = r#"[Event "Quoted \"name\" \\ here"]
+[SetUp "1"]
+[FEN "4k3/1P6/8/8/8/8/8/4K3 w - - 0 1"]
+%escaped line, ignored
+1. b8=Q+ Kd7 2.Qb5+ 1-0"#;
+        let games = read(pgn);
+        let game = games[0].as_ref().unwrap();
+        assert_eq!(Some(r#"Quoted "name" \ here"#), game.tag("Event"));
+        assert_eq!(Some(PieceType::Queen), game.mainline[0].m.promotion);
+        assert_eq!(3, game.moves().count());
+    }
+
+    #[test]
+    fn bad_games_are_skipped() {
+        let pgn = r#"[Event "Illegal"]
+
+1. e4 e5 2. Ke3 *
+
+[Event "Fine"]
+
+1. e4 *
+
+[Event "Bad FEN"]
+[FEN "8/8/8 w - - 0 1"]
+
+1. e4 *
+[Event "King en prise"]
+[FEN "4k3/8/8/8/8/8/8/4R1K1 w - - 0 1"]
+
+1. Rxe8 *
+[Event "Open variation"]
+1. e4 (1. d4 d5 *
+[Event "Unterminated comment"]
+1. e4 { never closed
+"#;
+        let games = read(pgn);
+        assert_eq!(6, games.len());
+
+        let error = games[0].as_ref().unwrap_err();
+        assert_eq!(3, error.line);
+        assert_eq!(
+            PgnErrorKind::San(SanError::IllegalMove("Ke3".to_string())),
+            error.kind
+        );
+        assert_eq!("line 3: illegal move 'Ke3'", error.to_string());
+
+        assert_eq!(1, games[1].as_ref().unwrap().mainline.len());
+        assert!(matches!(
+            games[2],
+            Err(PgnError {
+                line: 12,
+                kind: PgnErrorKind::Fen(_)
+            })
+        ));
+        assert_eq!(
+            Err(PgnError {
+                line: 16,
+                kind: PgnErrorKind::Fen(FenError::OpponentInCheck)
+            }),
+            games[3]
+        );
+        assert_eq!(
+            Err(PgnError {
+                line: 18,
+                kind: PgnErrorKind::UnterminatedVariation
+            }),
+            games[4]
+        );
+        assert_eq!(
+            Err(PgnError {
+                line: 20,
+                kind: PgnErrorKind::UnterminatedComment
+            }),
+            games[5]
+        );
+    }
+
+    #[test]
+    fn games_end_at_their_marker() {
+        let games = read("1. e4 e5 1-0\n\n1. d4 d5 0-1 1. c4 (1. Nf3 *) *\n{trailing comment}\n");
+        assert_eq!(3, games.len());
+
+        let uci = |i: usize| -> Vec<String> {
+            let game = games[i].as_ref().unwrap();
+            game.moves().map(|m| m.to_uci()).collect()
+        };
+        assert_eq!(vec!["e2e4", "e7e5"], uci(0));
+        assert_eq!(Some("1-0"), games[0].as_ref().unwrap().tag("Result"));
+        assert_eq!(vec!["d2d4", "d7d5"], uci(1));
+        assert_eq!(Some("0-1"), games[1].as_ref().unwrap().tag("Result"));
+        assert_eq!(vec!["c2c4"], uci(2));
+        assert_eq!(Some("*"), games[2].as_ref().unwrap().tag("Result"));
+    }
+
+    #[test]
+    fn latin1_games_are_read() {
+        let mut pgn = b"[White \"Caf".to_vec();
+        // e acute in Latin-1
+        pgn.push(0xe9);
+        pgn.extend_from_slice(b"\"]\n\n1. e4 *\n\n[Event \"Next\"]\n\n1. d4 *\n");
+
+        let games: Vec<_> = PgnReader::new(&pgn[..]).collect();
+        assert_eq!(2, games.len());
+        assert_eq!(Some("Caf\u{fffd}"), games[0].as_ref().unwrap().tag("White"));
+        assert_eq!(Some("Next"), games[1].as_ref().unwrap().tag("Event"));
+    }
+
+    #[test]
+    fn syntax_errors() {
+        let error = |pgn: &str| read(pgn).remove(0).unwrap_err();
+
+        assert_eq!(
+            PgnErrorKind::InvalidTag,
+            error("[Event unquoted]\n1. e4 *").kind
+        );
+        assert_eq!(
+            PgnErrorKind::UnexpectedChar('<'),
+            error("1. e4 <e5> *").kind
+        );
+        assert_eq!(PgnErrorKind::UnmatchedVariationEnd, error("1. e4 ) *").kind);
+        // the reader ends a game at its marker, parse_game is stricter
+        assert_eq!(
+            PgnErrorKind::UnexpectedMove("e5".to_string()),
+            parse_game("1. e4 * e5", 1).unwrap_err().kind
+        );
+        assert!(read("\n\n  \n").is_empty());
+    }
+
+    #[test]
+    fn write_games() {
+        let games = read(GAMES);
+        let game = games[1].as_ref().unwrap();
+        let expected = r#"[Event "Variations"]
+[Site "?"]
+[Date "????.??.??"]
+[Round "?"]
+[White "?"]
+[Black "?"]
+[Result "*"]
+
+1. d4 $1 d5 {rest of line comment} (1... Nf6 2. c4 (2. Nf3 g6) 2... e6) 2. c4 $5
+{a comment over two lines} 2... e6 *
+"#;
+        assert_eq!(expected, game.to_pgn().unwrap());
+
+        // long games are wrapped and read back unchanged
+        let game = games[0].as_ref().unwrap();
+        let pgn = game.to_pgn().unwrap();
+        assert!(pgn.lines().all(|line| line.len() <= PGN_LINE_LENGTH));
+        assert!(pgn.contains("5. O-O Be7"));
+        assert!(pgn.contains("Bxf7+") && pgn.contains("Rxe1+"));
+        assert!(pgn.ends_with("1/2-1/2\n"));
+        let read_back = read(&pgn).remove(0).unwrap();
+        assert_eq!(game.mainline, read_back.mainline);
+        assert_eq!(game.tags, read_back.tags);
+    }
+
+    #[test]
+    fn write_built_game() {
+        let board = Board::from_fen("4k3/1P6/8/8/8/8/8/4K3 b - - 0 40").unwrap();
+        let mut game = Game::from_board(&board);
+        game.set_tag("White", "A \"quoted\" name");
+        game.set_tag("Result", "1-0");
+
+        let mut after = board.clone();
+        let kd7 = after.parse_san("Kd7").unwrap();
+        after.do_move(&kd7);
+        let node = game.push(kd7);
+        node.comment_before = Some(String::from("Black resists"));
+        node.clock = Some(Duration::from_secs(59));
+        let promotion = after.parse_san("b8=Q").unwrap();
+        let node = game.push(promotion);
+        node.eval = Some(Eval::Mate(4));
+        node.nags.push(1);
+
+        let expected = r#"[Event "?"]
+[Site "?"]
+[Date "????.??.??"]
+[Round "?"]
+[White "A \"quoted\" name"]
+[Black "?"]
+[Result "1-0"]
+[SetUp "1"]
+[FEN "4k3/1P6/8/8/8/8/8/4K3 b - - 0 40"]
+
+{Black resists} 40... Kd7 {[%clk 0:00:59]} 41. b8=Q $1 {[%eval #4]} 1-0
+"#;
+        let pgn = game.to_pgn().unwrap();
+        assert_eq!(expected, pgn);
+
+        let read_back = read(&pgn).remove(0).unwrap();
+        assert_eq!(game.mainline, read_back.mainline);
+        assert_eq!(Some(r#"A "quoted" name"#), read_back.tag("White"));
+
+        // empty variations are left out
+        game.mainline[0].variations.push(Vec::new());
+        assert_eq!(expected, game.to_pgn().unwrap());
+
+        // a move that cannot be played there is refused
+        game.push(kd7);
+        assert_eq!(
+            Err(PgnErrorKind::San(SanError::IllegalMove("e8d7".to_string()))),
+            game.to_pgn()
+        );
+        game.mainline.pop();
+        // white moves in a variation for a black move
+        game.mainline[0]
+            .variations
+            .push(vec![MoveNode::new(promotion)]);
+        assert_eq!(
+            Err(PgnErrorKind::San(SanError::IllegalMove(
+                "b7b8q".to_string()
+            ))),
+            game.to_pgn()
+        );
+    }
+
+    #[test]
+    fn write_unsafe_text() {
+        let mut game = Game::new();
+        game.set_tag("Event", "two\nlines\r\n");
+        let e4 = Board::new().parse_san("e4").unwrap();
+        let node = game.push(e4);
+        node.comment = Some(String::from("closing } brace"));
+        node.comment_before = Some(String::from("}"));
+
+        let pgn = game.to_pgn().unwrap();
+        assert!(pgn.starts_with("[Event \"two lines  \"]\n"));
+        assert!(pgn.contains("{} 1. e4 {closing brace}"));
+
+        let read_back = read(&pgn).remove(0).unwrap();
+        assert_eq!(Some("two lines  "), read_back.tag("Event"));
+        assert_eq!(
+            Some("closing brace"),
+            read_back.mainline[0].comment.as_deref()
+        );
+        assert_eq!(pgn, read_back.to_pgn().unwrap());
+    }
+}
@@ -1,31 +1,63 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::engine::error::ConversionError;
+
 #[derive(PartialEq, Eq, Copy, Clone, Debug)]
 pub enum Color {
     White = 0,
     Black,
 }
 
-impl From<usize> for Color {
-    fn from(i: usize) -> Self {
-        match i {
-            x if x == Color::White as usize => Color::White,
-            x if x == Color::Black as usize => Color::Black,
-            _ => panic!("cannot convert {} to Color", i),
+impl Color {
+    // indexed by `color as usize`
+    pub const ALL: [Color; 2] = [Color::White, Color::Black];
+}
+
+impl TryFrom<usize> for Color {
+    type Error = ConversionError;
+
+    fn try_from(i: usize) -> Result<Self, Self::Error> {
+        Color::ALL
+            .get(i)
+            .copied()
+            .ok_or(ConversionError::ColorIndex(i))
+    }
+}
+
+impl FromStr for Color {
+    type Err = ConversionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "w" => Ok(Color::White),
+            "b" => Ok(Color::Black),
+            _ => Err(ConversionError::Color(s.to_string())),
         }
     }
 }
 
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Color::White => "w",
+            Color::Black => "b",
+        })
+    }
+}
+
 impl std::ops::Not for Color {
     type Output = Self;
 
     fn not(self) -> Self::Output {
         match self {
             Color::White => Color::Black,
-            Color::Black => Color::White
+            Color::Black => Color::White,
         }
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
 pub enum PieceType {
     Pawn = 0,
     Knight,
@@ -35,6 +67,18 @@ pub enum PieceType {
     King,
 }
 
+impl PieceType {
+    // indexed by `piece as usize`
+    pub const ALL: [PieceType; 6] = [
+        PieceType::Pawn,
+        PieceType::Knight,
+        PieceType::Bishop,
+        PieceType::Rook,
+        PieceType::Queen,
+        PieceType::King,
+    ];
+}
+
 impl From<PieceType> for char {
     fn from(p: PieceType) -> Self {
         match p {
@@ -48,16 +92,89 @@ impl From<PieceType> for char {
     }
 }
 
-impl From<usize> for PieceType {
-    fn from(i: usize) -> Self {
-        match i {
-            x if x == PieceType::Pawn as usize => PieceType::Pawn,
-            x if x == PieceType::Knight as usize => PieceType::Knight,
-            x if x == PieceType::Bishop as usize => PieceType::Bishop,
-            x if x == PieceType::Rook as usize => PieceType::Rook,
-            x if x == PieceType::Queen as usize => PieceType::Queen,
-            x if x == PieceType::King as usize => PieceType::King,
-            _ => panic!("cannot convert {} to PieceType", i),
+impl TryFrom<usize> for PieceType {
+    type Error = ConversionError;
+
+    fn try_from(i: usize) -> Result<Self, Self::Error> {
+        PieceType::ALL
+            .get(i)
+            .copied()
+            .ok_or(ConversionError::PieceIndex(i))
+    }
+}
+
+// either case, the color of a FEN letter is left to the caller
+impl TryFrom<char> for PieceType {
+    type Error = ConversionError;
+
+    fn try_from(c: char) -> Result<Self, Self::Error> {
+        match c.to_ascii_uppercase() {
+            'P' => Ok(PieceType::Pawn),
+            'N' => Ok(PieceType::Knight),
+            'B' => Ok(PieceType::Bishop),
+            'R' => Ok(PieceType::Rook),
+            'Q' => Ok(PieceType::Queen),
+            'K' => Ok(PieceType::King),
+            _ => Err(ConversionError::Piece(c.to_string())),
+        }
+    }
+}
+
+impl FromStr for PieceType {
+    type Err = ConversionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => PieceType::try_from(c),
+            _ => Err(ConversionError::Piece(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for PieceType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", char::from(*self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colors() {
+        assert_eq!(Ok(Color::Black), Color::try_from(1));
+        assert_eq!(Err(ConversionError::ColorIndex(2)), Color::try_from(2));
+
+        assert_eq!(Ok(Color::White), "w".parse());
+        assert_eq!(Ok(Color::Black), "b".parse());
+        for s in ["", "W", "white", "x"] {
+            assert_eq!(
+                Err(ConversionError::Color(s.to_string())),
+                s.parse::<Color>()
+            );
+        }
+        assert_eq!("w", Color::White.to_string());
+        assert_eq!("b", Color::Black.to_string());
+    }
+
+    #[test]
+    fn piece_types() {
+        for (i, piece) in PieceType::ALL.iter().enumerate() {
+            assert_eq!(Ok(*piece), PieceType::try_from(i));
+            assert_eq!(Ok(*piece), piece.to_string().parse());
+            assert_eq!(Ok(*piece), piece.to_string().to_lowercase().parse());
+        }
+        assert_eq!(Err(ConversionError::PieceIndex(6)), PieceType::try_from(6));
+
+        assert_eq!(Ok(PieceType::Knight), PieceType::try_from('n'));
+        assert_eq!("N", PieceType::Knight.to_string());
+        for s in ["", "X", "NB", "1"] {
+            assert_eq!(
+                Err(ConversionError::Piece(s.to_string())),
+                s.parse::<PieceType>()
+            );
         }
     }
 }
@@ -1,6 +1,11 @@
-use crate::engine::bits::BitOperations;
+use std::fmt;
+use std::str::FromStr;
 
-#[derive(PartialEq, Copy, Clone, Debug)]
+use crate::engine::bitboard::Bitboard;
+use crate::engine::error::ConversionError;
+use crate::engine::square::Square;
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
 pub enum Rank {
     One = 0,
     Two,
@@ -12,23 +17,32 @@ pub enum Rank {
     Eight,
 }
 
-impl From<usize> for Rank {
-    fn from(i: usize) -> Self {
-        match i {
-            x if x == Rank::One as usize => Rank::One,
-            x if x == Rank::Two as usize => Rank::Two,
-            x if x == Rank::Three as usize => Rank::Three,
-            x if x == Rank::Four as usize => Rank::Four,
-            x if x == Rank::Five as usize => Rank::Five,
-            x if x == Rank::Six as usize => Rank::Six,
-            x if x == Rank::Seven as usize => Rank::Seven,
-            x if x == Rank::Eight as usize => Rank::Eight,
-            _ => panic!("cannot convert {} to a rank", i),
-        }
+impl Rank {
+    // indexed by `rank as usize`
+    pub const ALL: [Rank; 8] = [
+        Rank::One,
+        Rank::Two,
+        Rank::Three,
+        Rank::Four,
+        Rank::Five,
+        Rank::Six,
+        Rank::Seven,
+        Rank::Eight,
+    ];
+}
+
+impl TryFrom<usize> for Rank {
+    type Error = ConversionError;
+
+    fn try_from(i: usize) -> Result<Self, Self::Error> {
+        Rank::ALL
+            .get(i)
+            .copied()
+            .ok_or(ConversionError::RankIndex(i))
     }
 }
 
-#[derive(PartialEq, Copy, Clone, Debug)]
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
 pub enum File {
     A = 0,
     B,
@@ -40,48 +54,109 @@ pub enum File {
     H,
 }
 
-impl From<usize> for File {
-    fn from(i: usize) -> Self {
-        match i {
-            x if x == File::A as usize => File::A,
-            x if x == File::B as usize => File::B,
-            x if x == File::C as usize => File::C,
-            x if x == File::D as usize => File::D,
-            x if x == File::E as usize => File::E,
-            x if x == File::F as usize => File::F,
-            x if x == File::G as usize => File::G,
-            x if x == File::H as usize => File::H,
-            _ => panic!("cannot convert {} to a file", i),
-        }
+impl File {
+    // indexed by `file as usize`
+    pub const ALL: [File; 8] = [
+        File::A,
+        File::B,
+        File::C,
+        File::D,
+        File::E,
+        File::F,
+        File::G,
+        File::H,
+    ];
+}
+
+impl TryFrom<usize> for File {
+    type Error = ConversionError;
+
+    fn try_from(i: usize) -> Result<Self, Self::Error> {
+        File::ALL
+            .get(i)
+            .copied()
+            .ok_or(ConversionError::FileIndex(i))
     }
 }
 
-#[derive(PartialEq, Copy, Clone, Debug)]
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
 pub struct Position {
     pub rank: Rank,
     pub file: File,
 }
 
-impl From<u64> for Position {
-    fn from(b: u64) -> Self {
-        if b == 0 {
-            panic!("attempt to create position from empty bitboard");
+// the lowest square of the bitboard
+impl TryFrom<Bitboard> for Position {
+    type Error = ConversionError;
+
+    fn try_from(b: Bitboard) -> Result<Self, Self::Error> {
+        if b.is_empty() {
+            return Err(ConversionError::EmptyBitboard);
         }
 
-        let index = b.lsb_index();
+        Ok(b.lsb().into())
+    }
+}
 
-        Position {
-            rank: (index / 8).into(),
-            file: (index % 8).into(),
-        }
+impl TryFrom<usize> for Position {
+    type Error = ConversionError;
+
+    fn try_from(sq: usize) -> Result<Self, Self::Error> {
+        Square::try_from(sq).map(Position::from)
     }
 }
 
-impl From<usize> for Position {
-    fn from(sq: usize) -> Self {
-        Position {
-            rank: (sq / 8).into(),
-            file: (sq % 8).into(),
-        }
+impl FromStr for Position {
+    type Err = ConversionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse::<Square>().map(Position::from)
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Square::from(*self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversions() {
+        let e4 = Position {
+            rank: Rank::Four,
+            file: File::E,
+        };
+
+        assert_eq!(Ok(e4), Position::try_from(28));
+        assert_eq!(
+            Err(ConversionError::SquareIndex(64)),
+            Position::try_from(64)
+        );
+        assert_eq!(Ok(e4), Position::try_from(Bitboard(0x10010000000)));
+        assert_eq!(
+            Err(ConversionError::EmptyBitboard),
+            Position::try_from(Bitboard::EMPTY)
+        );
+
+        assert_eq!(Ok(Rank::Eight), Rank::try_from(7));
+        assert_eq!(Err(ConversionError::RankIndex(8)), Rank::try_from(8));
+        assert_eq!(Ok(File::H), File::try_from(7));
+        assert_eq!(Err(ConversionError::FileIndex(8)), File::try_from(8));
+    }
+
+    #[test]
+    fn parse_and_display() {
+        let e4: Position = "e4".parse().unwrap();
+        assert_eq!(Rank::Four, e4.rank);
+        assert_eq!(File::E, e4.file);
+        assert_eq!("e4", e4.to_string());
+        assert_eq!(
+            Err(ConversionError::Square("e9".to_string())),
+            "e9".parse::<Position>()
+        );
     }
 }
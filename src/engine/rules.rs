@@ -1,160 +1,197 @@
-use crate::engine::bits::BitOperations;
+use crate::engine::bitboard::Bitboard;
 use crate::engine::board::Board;
+use crate::engine::move_list::MoveList;
 use crate::engine::piece::{Color, PieceType};
-use crate::engine::position::Position;
-use crate::engine::r#move::Move;
+use crate::engine::r#move::PackedMove;
 use crate::engine::rules_bb::*;
-
-fn gen_attack_vec(pos: Position, mut attacks: u64, piece: PieceType, enemies: u64) -> Vec<Move> {
-    let mut v = Vec::with_capacity(attacks.count_ones() as usize);
-
-    while attacks != 0 {
-        let sq = attacks.lsb_index();
-        v.push(Move {
-            start: pos.clone(),
-            end: sq.into(),
-            piece_type: piece.clone(),
-
-            is_capture: enemies.test_bit(sq),
-        });
-        attacks.lsb_pop();
+use crate::engine::square::Square;
+
+fn gen_attack_moves(v: &mut MoveList, from: Square, attacks: Bitboard, enemies: Bitboard) {
+    for to in attacks {
+        let flags = if enemies.contains(to) {
+            PackedMove::CAPTURE
+        } else {
+            PackedMove::QUIET
+        };
+        v.push(PackedMove::new(from, to, flags));
     }
+}
 
-    v
+const PROMOTIONS: [PieceType; 4] = [
+    PieceType::Queen,
+    PieceType::Rook,
+    PieceType::Bishop,
+    PieceType::Knight,
+];
+
+// targets are reached from `target - offset`
+fn gen_pawn_moves(v: &mut MoveList, targets: Bitboard, offset: isize, flags: u16) {
+    for to in targets {
+        let from = to.offset(-offset);
+
+        if BACK_RANKS.contains(to) {
+            for promotion in PROMOTIONS
+                .into_iter()
+                .filter_map(PackedMove::promotion_flags)
+            {
+                v.push(PackedMove::new(from, to, flags | promotion));
+            }
+        } else {
+            v.push(PackedMove::new(from, to, flags));
+        }
+    }
 }
 
-pub fn generate_pawn_moves(board: &Board) -> Vec<Move> {
-    let mut pawns = board[PieceType::Pawn];
-    let mut attacks = if board.side_to_move == Color::White {
-        pawns << 8
-    } else {
-        pawns >> 8
-    };
+pub fn generate_pawn_moves(board: &Board, v: &mut MoveList) {
+    let pawns = board[PieceType::Pawn];
+    let enemies = board.enemies_bb();
+    let empty = !(enemies | board.friends_bb());
 
-    let mut v = Vec::with_capacity(attacks.count_ones() as usize);
-    while attacks != 0 {
-        v.push(Move {
-            start: pawns.into(),
-            end: attacks.into(),
-            piece_type: PieceType::Pawn,
+    let (up, double_push_rank) = match board.side_to_move {
+        Color::White => (8, RANK_3),
+        Color::Black => (-8, RANK_6),
+    };
 
-            is_capture: false,
-        });
-        pawns.lsb_pop();
-        attacks.lsb_pop();
+    let single_pushes = pawns.shift(up) & empty;
+    let double_pushes = (single_pushes & double_push_rank).shift(up) & empty;
+    let east_captures = (pawns & NOT_H_FILE).shift(up + 1) & enemies;
+    let west_captures = (pawns & NOT_A_FILE).shift(up - 1) & enemies;
+
+    gen_pawn_moves(v, single_pushes, up, PackedMove::QUIET);
+    gen_pawn_moves(v, double_pushes, 2 * up, PackedMove::DOUBLE_PUSH);
+    gen_pawn_moves(v, east_captures, up + 1, PackedMove::CAPTURE);
+    gen_pawn_moves(v, west_captures, up - 1, PackedMove::CAPTURE);
+
+    if let Some(ep) = board.en_passant {
+        // our pawns standing where an enemy pawn on the target would attack,
+        // next to the enemy pawn that was just pushed past it
+        let enemy_pawns = board.pieces(PieceType::Pawn, !board.side_to_move);
+        for from in pawn_attacks_bb(ep, !board.side_to_move) & pawns {
+            if enemy_pawns.contains(Square::from_coords(from.rank(), ep.file())) {
+                v.push(PackedMove::new(from, ep, PackedMove::EN_PASSANT));
+            }
+        }
     }
-
-    v
 }
 
-pub fn generate_knight_moves(board: &Board) -> Vec<Move> {
+pub fn generate_knight_moves(board: &Board, v: &mut MoveList) {
     let enemies = board.enemies_bb();
     let friends = board.friends_bb();
 
-    let mut knights = board[PieceType::Knight];
-    let mut v = Vec::new();
-
-    while knights != 0 {
-        let sq = knights.lsb_pop();
-        let pos = Position::from(sq);
+    for sq in board[PieceType::Knight] {
         let attacks = exclude_friends(knight_attacks_bb(sq), friends);
-        v.append(&mut gen_attack_vec(
-            pos,
-            attacks,
-            PieceType::Knight,
-            enemies,
-        ));
+        gen_attack_moves(v, sq, attacks, enemies);
     }
-
-    v
 }
 
-pub fn generate_bishop_moves(board: &Board) -> Vec<Move> {
+pub fn generate_bishop_moves(board: &Board, v: &mut MoveList) {
     let enemies = board.enemies_bb();
     let friends = board.friends_bb();
     let blockers = enemies | friends;
 
-    let mut bishops = board[PieceType::Bishop];
-    let mut v = Vec::new();
-
-    while bishops != 0 {
-        let sq = bishops.lsb_pop();
-        let pos = Position::from(sq);
+    for sq in board[PieceType::Bishop] {
         let attacks = exclude_friends(bishop_attacks_bb(sq, blockers), friends);
-        v.append(&mut gen_attack_vec(
-            pos,
-            attacks,
-            PieceType::Bishop,
-            enemies,
-        ));
+        gen_attack_moves(v, sq, attacks, enemies);
     }
-
-    v
 }
 
-pub fn generate_rook_moves(board: &Board) -> Vec<Move> {
+pub fn generate_rook_moves(board: &Board, v: &mut MoveList) {
     let enemies = board.enemies_bb();
     let friends = board.friends_bb();
     let blockers = enemies | friends;
 
-    let mut rooks = board[PieceType::Rook];
-    let mut v = Vec::new();
-
-    while rooks != 0 {
-        let sq = rooks.lsb_pop();
-        let pos = Position::from(sq);
+    for sq in board[PieceType::Rook] {
         let attacks = exclude_friends(rook_attacks_bb(sq, blockers), friends);
-        v.append(&mut gen_attack_vec(pos, attacks, PieceType::Rook, enemies));
+        gen_attack_moves(v, sq, attacks, enemies);
     }
-
-    v
 }
 
-pub fn generate_queen_moves(board: &Board) -> Vec<Move> {
+pub fn generate_queen_moves(board: &Board, v: &mut MoveList) {
     let enemies = board.enemies_bb();
     let friends = board.friends_bb();
     let blockers = enemies | friends;
 
-    let mut queens = board[PieceType::Queen];
-    let mut v = Vec::new();
-
-    while queens != 0 {
-        let sq = queens.lsb_pop();
-        let pos = Position::from(sq);
+    for sq in board[PieceType::Queen] {
         let attacks = exclude_friends(queen_attacks_bb(sq, blockers), friends);
-        v.append(&mut gen_attack_vec(pos, attacks, PieceType::Queen, enemies));
+        gen_attack_moves(v, sq, attacks, enemies);
     }
-
-    v
 }
 
-pub fn generate_king_moves(board: &Board) -> Vec<Move> {
+pub fn generate_king_moves(board: &Board, v: &mut MoveList) {
     let king = board[PieceType::King];
     let enemies = board.enemies_bb();
     let friends = board.friends_bb();
 
-    let sq = king.lsb_index();
+    let sq = king.lsb();
     let attacks = exclude_friends(king_attacks_bb(sq), friends);
 
-    let start = Position::from(sq);
+    gen_attack_moves(v, sq, attacks, enemies);
+    gen_castling_moves(board, v);
+}
+
+// castling is encoded as the king moving two squares towards the rook
+fn gen_castling_moves(board: &Board, v: &mut MoveList) {
+    let (king_side, queen_side, home) = match board.side_to_move {
+        Color::White => (board.white_king_castling, board.white_queen_castling, 0),
+        Color::Black => (board.black_king_castling, board.black_queen_castling, 56),
+    };
+
+    let king = Square::new(home + 4);
+    if (!king_side && !queen_side) || !board[PieceType::King].contains(king) {
+        return;
+    }
+
+    let occupancy = board.occupancy_bb();
+    let enemies = board.enemies_bb();
+    let attacked = |sq| !(board.attackers_to(sq, occupancy) & enemies).is_empty();
+
+    // the king may neither castle out of, through nor into check
+    if attacked(king) {
+        return;
+    }
+
+    let rooks = board[PieceType::Rook];
+    let castle = |rook, path: u64, crossed: [isize; 2]| {
+        rooks.contains(Square::new(rook))
+            && (occupancy & Bitboard(path << home)).is_empty()
+            && !crossed.iter().any(|&delta| attacked(king.offset(delta)))
+    };
 
-    gen_attack_vec(start, attacks, PieceType::King, enemies)
+    if king_side && castle(home + 7, 0x60, [1, 2]) {
+        v.push(PackedMove::new(
+            king,
+            king.offset(2),
+            PackedMove::KING_CASTLE,
+        ));
+    }
+    if queen_side && castle(home, 0x0e, [-1, -2]) {
+        v.push(PackedMove::new(
+            king,
+            king.offset(-2),
+            PackedMove::QUEEN_CASTLE,
+        ));
+    }
 }
 
 #[cfg(test)]
 pub mod tests {
     use super::*;
-    use crate::engine::board::{tests::print_u64, self};
+    use crate::engine::board::tests::print_u64;
 
-    pub fn moves_to_u64(moves: &Vec<Move>) -> u64 {
-        moves.iter().fold(0u64, |b, m| {
-            b | (1 << (m.end.rank as u8 * 8 + m.end.file as u8))
-        })
+    pub fn moves_to_u64(moves: &[PackedMove]) -> u64 {
+        moves.iter().fold(0u64, |b, m| b | m.to_square().bb().0)
+    }
+
+    // runs a single generator into a fresh list
+    pub fn generate(generator: fn(&Board, &mut MoveList), board: &Board) -> MoveList {
+        let mut moves = MoveList::new();
+        generator(board, &mut moves);
+        moves
     }
 
     #[allow(dead_code)]
-    fn print_moves(moves: &Vec<Move>) {
-        let board = moves_to_u64(&moves);
+    fn print_moves(moves: &[PackedMove]) {
+        let board = moves_to_u64(moves);
         print_u64(board);
     }
 
@@ -162,67 +199,118 @@ pub mod tests {
     fn king_moves_empty() {
         let mut board = Board::empty();
 
-        board[PieceType::King] = 0x800000000; // d5
-        let res = generate_king_moves(&board);
+        board[PieceType::King] = Bitboard(0x800000000); // d5
+        let res = generate(generate_king_moves, &board);
         assert_eq!(0x1c141c000000, moves_to_u64(&res));
 
-        board[PieceType::King] = 0x1; // a1
-        let res = generate_king_moves(&board);
+        board[PieceType::King] = Bitboard(0x1); // a1
+        let res = generate(generate_king_moves, &board);
         assert_eq!(0x302, moves_to_u64(&res));
 
-        board[PieceType::King] = 0x80; // h8
-        let res = generate_king_moves(&board);
+        board[PieceType::King] = Bitboard(0x80); // h8
+        let res = generate(generate_king_moves, &board);
         assert_eq!(0xc040, moves_to_u64(&res));
 
-        board[PieceType::King] = 0x1000000000000000; // e8
-        let res = generate_king_moves(&board);
+        board[PieceType::King] = Bitboard(0x1000000000000000); // e8
+        let res = generate(generate_king_moves, &board);
         assert_eq!(0x2838000000000000, moves_to_u64(&res));
     }
 
     #[test]
     fn king_moves_blockers() {
         let mut board = Board::empty();
-        board[PieceType::King] = 0x1000000000000000; // e8
+        board[PieceType::King] = Bitboard(0x1000000000000000); // e8
 
-        board[PieceType::Pawn] = 0x820000000000000; // d8, f7
-        let res = generate_king_moves(&board);
+        board[PieceType::Pawn] = Bitboard(0x820000000000000); // d8, f7
+        let res = generate(generate_king_moves, &board);
         assert_eq!(0x2018000000000000, moves_to_u64(&res));
 
-        board[PieceType::Pawn] = 0u64;
-        board.set_bb(PieceType::Pawn, Color::Black, 0x820000000000000); // d8, f7
-        let res = generate_king_moves(&board);
+        board[PieceType::Pawn] = Bitboard::EMPTY;
+        board.set_bb(PieceType::Pawn, Color::Black, Bitboard(0x820000000000000)); // d8, f7
+        let res = generate(generate_king_moves, &board);
         assert_eq!(0x2838000000000000, moves_to_u64(&res));
     }
 
+    fn castling_targets(fen: &str) -> u64 {
+        let mut v = MoveList::new();
+        let board = Board::from_fen(fen).unwrap();
+        gen_castling_moves(&board, &mut v);
+        moves_to_u64(&v)
+    }
+
+    #[test]
+    fn king_moves_castling() {
+        let board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let res = generate(generate_king_moves, &board);
+        assert_eq!(0x386c, moves_to_u64(&res));
+
+        assert_eq!(
+            0x4400000000000000,
+            castling_targets("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1")
+        );
+        assert_eq!(0x40, castling_targets("r3k2r/8/8/8/8/8/8/R3K2R w Kq - 0 1"));
+        assert_eq!(0x4, castling_targets("r3k2r/8/8/8/8/8/8/R3K2R w Qk - 0 1"));
+    }
+
+    #[test]
+    fn king_moves_castling_blockers() {
+        // pieces in between, whatever their color
+        assert_eq!(
+            0,
+            castling_targets("r3k2r/8/8/8/8/8/8/RN2K1nR w KQkq - 0 1")
+        );
+        assert_eq!(
+            0x40,
+            castling_targets("r3k2r/8/8/8/8/8/8/R2QK2R w KQkq - 0 1")
+        );
+        // no rook to castle with, which from_fen refuses
+        let mut board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        board.set_bb(PieceType::Rook, Color::White, Bitboard(0x1));
+        let mut v = MoveList::new();
+        gen_castling_moves(&board, &mut v);
+        assert_eq!(0x4, moves_to_u64(&v));
+    }
+
+    #[test]
+    fn king_moves_castling_attacked() {
+        // in check
+        assert_eq!(0, castling_targets("4r1k1/8/8/8/8/8/8/R3K2R w KQ - 0 1"));
+        // crossing or landing on an attacked square
+        assert_eq!(0x4, castling_targets("5rk1/8/8/8/8/8/8/R3K2R w KQ - 0 1"));
+        assert_eq!(0x40, castling_targets("2r3k1/8/8/8/8/8/8/R3K2R w KQ - 0 1"));
+        // only the squares the king walks on matter
+        assert_eq!(0x44, castling_targets("1r4k1/8/8/8/8/8/8/R3K2R w KQ - 0 1"));
+    }
+
     #[test]
     fn knight_moves_empty() {
         let mut board = Board::empty();
 
-        board[PieceType::Knight] = 0x42; // initial white pos
-        let res = generate_knight_moves(&board);
+        board[PieceType::Knight] = Bitboard(0x42); // initial white pos
+        let res = generate(generate_knight_moves, &board);
         assert_eq!(0xa51800, moves_to_u64(&res));
 
-        board[PieceType::Knight] = 0x400000000; // one knight on c5
-        let res = generate_knight_moves(&board);
+        board[PieceType::Knight] = Bitboard(0x400000000); // one knight on c5
+        let res = generate(generate_knight_moves, &board);
         assert_eq!(0xa1100110a0000, moves_to_u64(&res));
 
-        board[PieceType::Knight] = 0; // no knight
-        let res = generate_knight_moves(&board);
+        board[PieceType::Knight] = Bitboard::EMPTY; // no knight
+        let res = generate(generate_knight_moves, &board);
         assert!(res.is_empty());
     }
 
     #[test]
     fn knight_moves_blockers() {
         let mut board = Board::empty();
-        board[PieceType::Knight] = 0x400000000; // one knight on c5
+        board[PieceType::Knight] = Bitboard(0x400000000); // one knight on c5
 
-        board[PieceType::Pawn] = 0x8020001080000; // a4, b6, d3, d7
-        let res = generate_knight_moves(&board);
+        board[PieceType::Pawn] = Bitboard(0x8020001080000); // a4, b6, d3, d7
+        let res = generate(generate_knight_moves, &board);
         assert_eq!(0x2110010020000, moves_to_u64(&res));
 
-        board[PieceType::Pawn] = 0u64;
-        board.set_bb(PieceType::Pawn, Color::Black, 0x8020001080000); // a4, b6, d3, d7
-        let res = generate_knight_moves(&board);
+        board[PieceType::Pawn] = Bitboard::EMPTY;
+        board.set_bb(PieceType::Pawn, Color::Black, Bitboard(0x8020001080000)); // a4, b6, d3, d7
+        let res = generate(generate_knight_moves, &board);
         assert_eq!(0xa1100110a0000, moves_to_u64(&res));
     }
 
@@ -230,8 +318,8 @@ pub mod tests {
     fn rook_moves_empty() {
         let mut board = Board::empty();
 
-        board[PieceType::Rook] = 0x200100000000; // c6 & h5
-        let res = generate_rook_moves(&board);
+        board[PieceType::Rook] = Bitboard(0x200100000000); // c6 & h5
+        let res = generate(generate_rook_moves, &board);
         assert_eq!(0x2121dffe21212121, moves_to_u64(&res));
         assert_eq!(28, res.len());
     }
@@ -239,15 +327,15 @@ pub mod tests {
     #[test]
     fn rook_moves_blockers() {
         let mut board = Board::empty();
-        board[PieceType::Rook] = 0x200100000000; // c6 & h5
+        board[PieceType::Rook] = Bitboard(0x200100000000); // c6 & h5
 
-        board[PieceType::Pawn] = 0x2100444000002100; // lots of blockers
-        let res = generate_rook_moves(&board);
+        board[PieceType::Pawn] = Bitboard(0x2100444000002100); // lots of blockers
+        let res = generate(generate_rook_moves, &board);
         assert_eq!(0x21193e21210000, moves_to_u64(&res));
 
-        board[PieceType::Pawn] = 0u64;
-        board.set_bb(PieceType::Pawn, Color::Black, 0x2100444000002100);
-        let res = generate_rook_moves(&board);
+        board[PieceType::Pawn] = Bitboard::EMPTY;
+        board.set_bb(PieceType::Pawn, Color::Black, Bitboard(0x2100444000002100));
+        let res = generate(generate_rook_moves, &board);
         assert_eq!(0x21215d7e21212100, moves_to_u64(&res));
     }
 
@@ -255,35 +343,35 @@ pub mod tests {
     fn bishop_moves_empty() {
         let mut board = Board::empty();
 
-        board[PieceType::Bishop] = 0x4000000; // c4
-        let res = generate_bishop_moves(&board);
+        board[PieceType::Bishop] = Bitboard(0x4000000); // c4
+        let res = generate(generate_bishop_moves, &board);
         assert_eq!(0x4020110a000a1120, moves_to_u64(&res));
 
-        board[PieceType::Bishop] = 0x200000; // f3
-        let res = generate_bishop_moves(&board);
+        board[PieceType::Bishop] = Bitboard(0x200000); // f3
+        let res = generate(generate_bishop_moves, &board);
         assert_eq!(0x102048850005088, moves_to_u64(&res));
 
-        board[PieceType::Bishop] = 0x80000000000000; // H7
-        let res = generate_bishop_moves(&board);
+        board[PieceType::Bishop] = Bitboard(0x80000000000000); // H7
+        let res = generate(generate_bishop_moves, &board);
         assert_eq!(0x4000402010080402, moves_to_u64(&res));
 
-        board[PieceType::Bishop] = 0x8000000000000; // D7
-        let res = generate_bishop_moves(&board);
+        board[PieceType::Bishop] = Bitboard(0x8000000000000); // D7
+        let res = generate(generate_bishop_moves, &board);
         assert_eq!(0x1400142241800000, moves_to_u64(&res));
     }
 
     #[test]
     fn bishop_moves_blockers() {
         let mut board = Board::empty();
-        board[PieceType::Bishop] = 0x4000000; // c4
+        board[PieceType::Bishop] = Bitboard(0x4000000); // c4
 
-        board[PieceType::Pawn] = 0x20010020020000; // a6, b3, f7, f4
-        let res = generate_bishop_moves(&board);
+        board[PieceType::Pawn] = Bitboard(0x20010020020000); // a6, b3, f7, f4
+        let res = generate(generate_bishop_moves, &board);
         assert_eq!(0x100a00081020, moves_to_u64(&res));
 
-        board[PieceType::Pawn] = 0u64; // remove white blockers
-        board.set_bb(PieceType::Pawn, Color::Black, 0x20010020020000); // a6, b3, f7, f4
-        let res = generate_bishop_moves(&board);
+        board[PieceType::Pawn] = Bitboard::EMPTY; // remove white blockers
+        board.set_bb(PieceType::Pawn, Color::Black, Bitboard(0x20010020020000)); // a6, b3, f7, f4
+        let res = generate(generate_bishop_moves, &board);
         assert_eq!(0x20110a000a1020, moves_to_u64(&res));
     }
 
@@ -291,41 +379,80 @@ pub mod tests {
     fn pawn_moves_empty() {
         let mut board = Board::empty();
 
-        board[PieceType::Pawn] = 0x20429d00;
-        let res = generate_pawn_moves(&board);
-        assert_eq!(0x20429d0000, moves_to_u64(&res));
+        board[PieceType::Pawn] = Bitboard(0x20429d00);
+        let res = generate(generate_pawn_moves, &board);
+        assert_eq!(0x20df9d0000, moves_to_u64(&res));
 
         board.side_to_move = Color::Black;
-        board[PieceType::Pawn] = 0x20429d00000000;
-        let res = generate_pawn_moves(&board);
-        assert_eq!(0x20429d000000, moves_to_u64(&res));
+        board[PieceType::Pawn] = Bitboard(0x20429d00000000);
+        let res = generate(generate_pawn_moves, &board);
+        // the white pawn on f4 can be taken from e5
+        assert_eq!(0x2062bd000000, moves_to_u64(&res));
+    }
+
+    #[test]
+    fn pawn_moves_blockers() {
+        let mut board = Board::empty();
+        board[PieceType::Pawn] = Bitboard(0x1800); // d2, e2
+
+        board.set_bb(PieceType::Knight, Color::Black, Bitboard(0x8100000)); // d4, e3
+        let res = generate(generate_pawn_moves, &board);
+        assert_eq!(0x180000, moves_to_u64(&res));
+        assert_eq!(2, res.len());
+    }
+
+    #[test]
+    fn pawn_moves_promotions() {
+        let mut board = Board::empty();
+        board[PieceType::Pawn] = Bitboard(0x2000000000000); // b7
+
+        board.set_bb(PieceType::Rook, Color::Black, Bitboard(0x500000000000000)); // a8, c8
+        let res = generate(generate_pawn_moves, &board);
+        assert_eq!(0x700000000000000, moves_to_u64(&res));
+        assert_eq!(12, res.len());
+        assert!(res.iter().all(|m| m.promotion().is_some()));
+        assert_eq!(8, res.iter().filter(|m| m.is_capture()).count());
+    }
+
+    #[test]
+    fn pawn_moves_en_passant() {
+        let board = Board::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+        let res = generate(generate_pawn_moves, &board);
+        assert_eq!(0x180000000000, moves_to_u64(&res));
+        assert_eq!(1, res.iter().filter(|m| m.is_capture()).count());
+
+        // a target square with no enemy pawn to take is ignored
+        let mut board = Board::from_fen("4k3/8/8/8/8/8/3PP3/4K3 w - - 0 1").unwrap();
+        board.en_passant = Some("e3".parse().unwrap());
+        let res = generate(generate_pawn_moves, &board);
+        assert!(res.iter().all(|m| !m.is_en_passant()));
     }
 
     #[test]
     fn queen_moves_empty() {
         let mut board = Board::empty();
 
-        board[PieceType::Queen] = 0x4000000; // c4
-        let res = generate_queen_moves(&board);
+        board[PieceType::Queen] = Bitboard(0x4000000); // c4
+        let res = generate(generate_queen_moves, &board);
         assert_eq!(0x4424150efb0e1524, moves_to_u64(&res));
 
-        board[PieceType::Queen] = 0x4008000; // c4 and h2
-        let res = generate_queen_moves(&board);
+        board[PieceType::Queen] = Bitboard(0x4008000); // c4 and h2
+        let res = generate(generate_queen_moves, &board);
         assert_eq!(0xc6a49d9efbce7fe4, moves_to_u64(&res));
     }
 
     #[test]
     fn queen_moves_blockers() {
         let mut board = Board::empty();
-        board[PieceType::Queen] = 0x4000000; // c4
+        board[PieceType::Queen] = Bitboard(0x4000000); // c4
 
-        board[PieceType::Pawn] = 0x100041040000; // a4, c3, e6, g4
-        let res = generate_queen_moves(&board);
+        board[PieceType::Pawn] = Bitboard(0x100041040000); // a4, c3, e6, g4
+        let res = generate(generate_queen_moves, &board);
         assert_eq!(0x404050e3a0a1120, moves_to_u64(&res));
 
-        board[PieceType::Pawn] = 0u64; // remove white blockers
-        board.set_bb(PieceType::Pawn, Color::Black, 0x100041040000); // a4, c3, e6, g4
-        let res = generate_queen_moves(&board);
+        board[PieceType::Pawn] = Bitboard::EMPTY; // remove white blockers
+        board.set_bb(PieceType::Pawn, Color::Black, Bitboard(0x100041040000)); // a4, c3, e6, g4
+        let res = generate(generate_queen_moves, &board);
         assert_eq!(0x404150e7b0e1120, moves_to_u64(&res));
     }
 }
@@ -1,27 +1,37 @@
-const NOT_A_FILE: u64 = 0xfefefefefefefefe;
-const NOT_H_FILE: u64 = 0x7f7f7f7f7f7f7f7f;
+use crate::engine::bitboard::Bitboard;
+use crate::engine::magic::{bishop_table, rook_table};
+#[cfg(all(feature = "pext", target_arch = "x86_64"))]
+use crate::engine::pext;
+use crate::engine::piece::Color;
+use crate::engine::square::Square;
 
-fn south_one(bb: u64) -> u64 {
+pub const NOT_A_FILE: Bitboard = Bitboard(0xfefefefefefefefe);
+pub const NOT_H_FILE: Bitboard = Bitboard(0x7f7f7f7f7f7f7f7f);
+pub const RANK_3: Bitboard = Bitboard(0x0000000000ff0000);
+pub const RANK_6: Bitboard = Bitboard(0x0000ff0000000000);
+pub const BACK_RANKS: Bitboard = Bitboard(0xff000000000000ff);
+
+const fn south_one(bb: u64) -> u64 {
     bb >> 8
 }
 
-fn north_one(bb: u64) -> u64 {
+const fn north_one(bb: u64) -> u64 {
     bb << 8
 }
 
-fn east_one(bb: u64) -> u64 {
-    (bb << 1) & NOT_A_FILE
+const fn east_one(bb: u64) -> u64 {
+    (bb << 1) & NOT_A_FILE.0
 }
 
-fn west_one(bb: u64) -> u64 {
-    (bb >> 1) & NOT_H_FILE
+const fn west_one(bb: u64) -> u64 {
+    (bb >> 1) & NOT_H_FILE.0
 }
 
-pub fn exclude_friends(attacks: u64, friends: u64) -> u64 {
-    attacks ^ (attacks & friends)
+pub fn exclude_friends(attacks: Bitboard, friends: Bitboard) -> Bitboard {
+    attacks & !friends
 }
 
-pub fn king_attacks_bb(sq: usize) -> u64 {
+const fn compute_king_attacks(sq: usize) -> u64 {
     let mut king = 1u64 << sq;
     let mut attacks = east_one(king) | west_one(king);
     king |= attacks;
@@ -30,7 +40,11 @@ pub fn king_attacks_bb(sq: usize) -> u64 {
     attacks
 }
 
-pub fn bishop_attacks_bb(sq: usize, blockers: u64) -> u64 {
+pub fn king_attacks_bb(sq: Square) -> Bitboard {
+    Bitboard(KING_ATTACKS[sq.index()])
+}
+
+pub(crate) fn bishop_attacks_slow(sq: usize, blockers: u64) -> u64 {
     let mut attacks = 0;
 
     let mut fill_until_blocker =
@@ -60,7 +74,7 @@ pub fn bishop_attacks_bb(sq: usize, blockers: u64) -> u64 {
     attacks
 }
 
-pub fn rook_attacks_bb(sq: usize, blockers: u64) -> u64 {
+pub(crate) fn rook_attacks_slow(sq: usize, blockers: u64) -> u64 {
     let mut attacks = 0;
 
     let mut fill_until_blocker =
@@ -89,7 +103,25 @@ pub fn rook_attacks_bb(sq: usize, blockers: u64) -> u64 {
     attacks
 }
 
-pub fn knight_attacks_bb(sq: usize) -> u64 {
+pub fn bishop_attacks_bb(sq: Square, blockers: Bitboard) -> Bitboard {
+    #[cfg(all(feature = "pext", target_arch = "x86_64"))]
+    if let Some(table) = pext::bishop_table() {
+        return Bitboard(table.attacks(sq.index(), blockers.0));
+    }
+
+    Bitboard(bishop_table().attacks(sq.index(), blockers.0))
+}
+
+pub fn rook_attacks_bb(sq: Square, blockers: Bitboard) -> Bitboard {
+    #[cfg(all(feature = "pext", target_arch = "x86_64"))]
+    if let Some(table) = pext::rook_table() {
+        return Bitboard(table.attacks(sq.index(), blockers.0));
+    }
+
+    Bitboard(rook_table().attacks(sq.index(), blockers.0))
+}
+
+const fn compute_knight_attacks(sq: usize) -> u64 {
     let bb = 1u64 << sq;
 
     let mut east = east_one(bb);
@@ -104,6 +136,191 @@ pub fn knight_attacks_bb(sq: usize) -> u64 {
     attacks
 }
 
-pub fn queen_attacks_bb(sq: usize, blockers: u64) -> u64 {
+pub fn knight_attacks_bb(sq: Square) -> Bitboard {
+    Bitboard(KNIGHT_ATTACKS[sq.index()])
+}
+
+pub fn queen_attacks_bb(sq: Square, blockers: Bitboard) -> Bitboard {
     rook_attacks_bb(sq, blockers) | bishop_attacks_bb(sq, blockers)
 }
+
+const fn compute_pawn_attacks(sq: usize, color: Color) -> u64 {
+    let bb = 1u64 << sq;
+    let attacks = east_one(bb) | west_one(bb);
+
+    match color {
+        Color::White => north_one(attacks),
+        Color::Black => south_one(attacks),
+    }
+}
+
+pub fn pawn_attacks_bb(sq: Square, color: Color) -> Bitboard {
+    Bitboard(PAWN_ATTACKS[color as usize][sq.index()])
+}
+
+// unit step (rank, file) from a towards b, if they share a rank, file or diagonal
+const fn direction(a: usize, b: usize) -> Option<(isize, isize)> {
+    let dr = (b / 8) as isize - (a / 8) as isize;
+    let df = (b % 8) as isize - (a % 8) as isize;
+
+    if a == b || (dr != 0 && df != 0 && dr.abs() != df.abs()) {
+        None
+    } else {
+        Some((dr.signum(), df.signum()))
+    }
+}
+
+// squares from sq (excluded) in direction (dr, df), until the edge of the board
+// or until `to` (excluded)
+const fn ray(sq: usize, dr: isize, df: isize, to: Option<usize>) -> u64 {
+    let mut ray = 0;
+    let mut r = (sq / 8) as isize + dr;
+    let mut f = (sq % 8) as isize + df;
+
+    while r >= 0 && r < 8 && f >= 0 && f < 8 {
+        let next = (r * 8 + f) as usize;
+        if let Some(to) = to {
+            if next == to {
+                break;
+            }
+        }
+        ray |= 1u64 << next;
+        r += dr;
+        f += df;
+    }
+
+    ray
+}
+
+const fn compute_between(a: usize, b: usize) -> u64 {
+    match direction(a, b) {
+        Some((dr, df)) => ray(a, dr, df, Some(b)),
+        None => 0,
+    }
+}
+
+const fn compute_line(a: usize, b: usize) -> u64 {
+    match direction(a, b) {
+        Some((dr, df)) => ray(a, dr, df, None) | ray(a, -dr, -df, None) | (1u64 << a),
+        None => 0,
+    }
+}
+
+// squares strictly between a and b, empty if they are not on a common line
+pub fn between_bb(a: Square, b: Square) -> Bitboard {
+    Bitboard(BETWEEN[a.index()][b.index()])
+}
+
+// the whole line going through a and b, edge to edge, empty if there is none
+pub fn line_bb(a: Square, b: Square) -> Bitboard {
+    Bitboard(LINE[a.index()][b.index()])
+}
+
+static KING_ATTACKS: [u64; 64] = {
+    let mut table = [0; 64];
+    let mut sq = 0;
+    while sq < 64 {
+        table[sq] = compute_king_attacks(sq);
+        sq += 1;
+    }
+    table
+};
+
+static KNIGHT_ATTACKS: [u64; 64] = {
+    let mut table = [0; 64];
+    let mut sq = 0;
+    while sq < 64 {
+        table[sq] = compute_knight_attacks(sq);
+        sq += 1;
+    }
+    table
+};
+
+static PAWN_ATTACKS: [[u64; 64]; 2] = {
+    let mut table = [[0; 64]; 2];
+    let mut sq = 0;
+    while sq < 64 {
+        table[Color::White as usize][sq] = compute_pawn_attacks(sq, Color::White);
+        table[Color::Black as usize][sq] = compute_pawn_attacks(sq, Color::Black);
+        sq += 1;
+    }
+    table
+};
+
+static BETWEEN: [[u64; 64]; 64] = {
+    let mut table = [[0; 64]; 64];
+    let mut a = 0;
+    while a < 64 {
+        let mut b = 0;
+        while b < 64 {
+            table[a][b] = compute_between(a, b);
+            b += 1;
+        }
+        a += 1;
+    }
+    table
+};
+
+static LINE: [[u64; 64]; 64] = {
+    let mut table = [[0; 64]; 64];
+    let mut a = 0;
+    while a < 64 {
+        let mut b = 0;
+        while b < 64 {
+            table[a][b] = compute_line(a, b);
+            b += 1;
+        }
+        a += 1;
+    }
+    table
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sq(s: &str) -> Square {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn leaper_tables() {
+        assert_eq!(Bitboard(0x302), king_attacks_bb(sq("a1")));
+        assert_eq!(Bitboard(0x1c141c000000), king_attacks_bb(sq("d5")));
+        assert_eq!(Bitboard(0xa1100110a0000), knight_attacks_bb(sq("c5")));
+        assert_eq!(Bitboard(0x20400), knight_attacks_bb(sq("a1")));
+        assert_eq!(Bitboard(0x280000), pawn_attacks_bb(sq("e2"), Color::White));
+        assert_eq!(Bitboard(0x28), pawn_attacks_bb(sq("e2"), Color::Black));
+        assert_eq!(
+            Bitboard(0x200000000),
+            pawn_attacks_bb(sq("a4"), Color::White)
+        );
+        assert_eq!(
+            Bitboard(0x40000000),
+            pawn_attacks_bb(sq("h5"), Color::Black)
+        );
+    }
+
+    #[test]
+    fn between_and_line() {
+        let diagonal = Bitboard(0x8040201008040201);
+        assert_eq!(Bitboard(0x0040201008040200), between_bb(sq("a1"), sq("h8")));
+        assert_eq!(diagonal, line_bb(sq("a1"), sq("h8")));
+        assert_eq!(diagonal, line_bb(sq("d4"), sq("b2")));
+
+        let e_file = Bitboard(0x1010101010101010);
+        assert_eq!(Bitboard(0x0010101010101000), between_bb(sq("e1"), sq("e8")));
+        assert_eq!(e_file, line_bb(sq("e1"), sq("e8")));
+
+        assert_eq!(Bitboard(0x7e), between_bb(sq("a1"), sq("h1")));
+        assert_eq!(Bitboard(0x7e), between_bb(sq("h1"), sq("a1")));
+        assert_eq!(Bitboard(0xff), line_bb(sq("d1"), sq("c1")));
+
+        // neighbours, not aligned, same square
+        assert_eq!(Bitboard::EMPTY, between_bb(sq("a1"), sq("b1")));
+        assert_eq!(Bitboard(0x3), line_bb(sq("a1"), sq("b1")) & Bitboard(0x3));
+        assert_eq!(Bitboard::EMPTY, between_bb(sq("a1"), sq("c2")));
+        assert_eq!(Bitboard::EMPTY, line_bb(sq("a1"), sq("c2")));
+        assert_eq!(Bitboard::EMPTY, line_bb(sq("f1"), sq("f1")));
+    }
+}
@@ -0,0 +1,297 @@
+use std::fmt;
+
+use crate::engine::board::Board;
+use crate::engine::piece::PieceType;
+use crate::engine::position::{File, Rank};
+use crate::engine::r#move::{Move, PackedMove};
+use crate::engine::square::Square;
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum SanError {
+    InvalidFormat(String),
+    IllegalMove(String),
+    // several legal moves match, e.g. Nd2 with knights on b1 and f3
+    AmbiguousMove(String),
+}
+
+impl fmt::Display for SanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SanError::InvalidFormat(s) => write!(f, "invalid SAN move '{}'", s),
+            SanError::IllegalMove(s) => write!(f, "illegal move '{}'", s),
+            SanError::AmbiguousMove(s) => write!(f, "ambiguous move '{}'", s),
+        }
+    }
+}
+
+impl std::error::Error for SanError {}
+
+// what a SAN string tells about the move it stands for
+struct SanPattern {
+    piece_type: PieceType,
+    castling: Option<u16>,
+    from_file: Option<File>,
+    from_rank: Option<Rank>,
+    to: Option<Square>,
+    promotion: Option<PieceType>,
+}
+
+impl SanPattern {
+    fn castling(flags: u16) -> Self {
+        SanPattern {
+            piece_type: PieceType::King,
+            castling: Some(flags),
+            from_file: None,
+            from_rank: None,
+            to: None,
+            promotion: None,
+        }
+    }
+
+    fn parse(san: &str) -> Option<Self> {
+        // check marks and annotations such as !? carry no information
+        let san = san.trim_end_matches(['+', '#', '!', '?']);
+        match san {
+            "O-O" | "0-0" => return Some(Self::castling(PackedMove::KING_CASTLE)),
+            "O-O-O" | "0-0-0" => return Some(Self::castling(PackedMove::QUEEN_CASTLE)),
+            _ => {}
+        }
+        if !san.is_ascii() {
+            return None;
+        }
+
+        let (piece_type, mut rest) = match san.chars().next()? {
+            c @ ('N' | 'B' | 'R' | 'Q' | 'K') => (PieceType::try_from(c).ok()?, &san[1..]),
+            _ => (PieceType::Pawn, san),
+        };
+
+        // e8=Q, also accepting e8Q and e8q
+        let mut promotion = None;
+        if piece_type == PieceType::Pawn {
+            if let Some(c) = rest.chars().last().filter(|c| "NBRQnbrq".contains(*c)) {
+                promotion = Some(PieceType::try_from(c).ok()?);
+                rest = rest[..rest.len() - 1].trim_end_matches('=');
+            }
+        }
+
+        let to = rest.get(rest.len().checked_sub(2)?..)?.parse().ok()?;
+        let mut from_file = None;
+        let mut from_rank = None;
+        for c in rest[..rest.len() - 2].chars() {
+            match c {
+                'a'..='h' if from_file.is_none() && from_rank.is_none() => {
+                    from_file = Some(File::ALL[c as usize - 'a' as usize])
+                }
+                '1'..='8' if from_rank.is_none() => {
+                    from_rank = Some(Rank::ALL[c as usize - '1' as usize])
+                }
+                'x' | ':' | '-' => {}
+                _ => return None,
+            }
+        }
+
+        Some(SanPattern {
+            piece_type,
+            castling: None,
+            from_file,
+            from_rank,
+            to: Some(to),
+            promotion,
+        })
+    }
+
+    fn matches(&self, board: &Board, m: PackedMove) -> bool {
+        let from = m.from_square();
+        let piece_type = board[from].map(|(piece_type, _)| piece_type);
+
+        piece_type == Some(self.piece_type)
+            && self.castling.map_or(true, |flags| m.flags() == flags)
+            && self.to.map_or(true, |to| m.to_square() == to)
+            && self.from_file.map_or(true, |file| from.file() == file)
+            && self.from_rank.map_or(true, |rank| from.rank() == rank)
+            && m.promotion() == self.promotion
+    }
+}
+
+impl Board {
+    // standard algebraic notation of the legal move m, such as Nbd2, exf6,
+    // e8=Q+ or O-O-O
+    pub fn to_san(&self, m: &Move) -> String {
+        let packed = self.pack_move(m);
+        let from = packed.from_square();
+        let to = packed.to_square();
+
+        let mut san = match packed.flags() {
+            PackedMove::KING_CASTLE => String::from("O-O"),
+            PackedMove::QUEEN_CASTLE => String::from("O-O-O"),
+            _ => {
+                let mut san = String::new();
+                if m.piece_type == PieceType::Pawn {
+                    if packed.is_capture() {
+                        san.push_str(&from.to_string()[..1]);
+                    }
+                } else {
+                    san.push(char::from(m.piece_type));
+
+                    // other pieces of the same type reaching the same square
+                    let others: Vec<Square> = self
+                        .generate_legal_moves()
+                        .iter()
+                        .filter(|other| other.to_square() == to && other.from_square() != from)
+                        .map(|other| other.from_square())
+                        .filter(|sq| self[*sq].map(|(p, _)| p) == Some(m.piece_type))
+                        .collect();
+
+                    let square = from.to_string();
+                    if others.is_empty() {
+                        // no disambiguation needed
+                    } else if others.iter().all(|sq| sq.file() != from.file()) {
+                        san.push_str(&square[..1]);
+                    } else if others.iter().all(|sq| sq.rank() != from.rank()) {
+                        san.push_str(&square[1..]);
+                    } else {
+                        san.push_str(&square);
+                    }
+                }
+
+                if packed.is_capture() {
+                    san.push('x');
+                }
+                san.push_str(&to.to_string());
+                if let Some(piece) = packed.promotion() {
+                    san.push('=');
+                    san.push(char::from(piece));
+                }
+                san
+            }
+        };
+
+        let mut after = self.clone();
+        after.make_move(packed);
+        if after.is_checkmate() {
+            san.push('#');
+        } else if after.is_check() {
+            san.push('+');
+        }
+
+        san
+    }
+
+    // the legal move written `san`, tolerating a missing x, 0-0 for castling
+    // and trailing check marks or annotations
+    pub fn parse_san(&self, san: &str) -> Result<Move, SanError> {
+        let pattern =
+            SanPattern::parse(san).ok_or_else(|| SanError::InvalidFormat(san.to_string()))?;
+
+        let mut found = None;
+        for m in self.generate_legal_moves() {
+            if pattern.matches(self, m) {
+                if found.is_some() {
+                    return Err(SanError::AmbiguousMove(san.to_string()));
+                }
+                found = Some(m);
+            }
+        }
+
+        found
+            .and_then(|m| self.unpack_move(m))
+            .ok_or_else(|| SanError::IllegalMove(san.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::board::tests::TEST_FENS;
+
+    fn sans(fen: &str) -> Vec<String> {
+        let board = Board::from_fen(fen).unwrap();
+        board
+            .generate_legal_moves()
+            .into_iter()
+            .map(|m| board.to_san(&board.unpack_move(m).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn san_of_moves() {
+        let initial = sans(TEST_FENS[0]);
+        for san in ["e4", "a3", "Nf3", "Na3"] {
+            assert!(initial.contains(&san.to_string()), "{}", san);
+        }
+
+        let kiwipete = sans(TEST_FENS[1]);
+        for san in [
+            "O-O", "O-O-O", "Nxf7", "Bxa6", "dxe6", "Qxf6", "gxh3", "Nxg6",
+        ] {
+            assert!(kiwipete.contains(&san.to_string()), "{}", san);
+        }
+
+        // disambiguation by file, rank, and both
+        assert!(sans("4k3/8/8/8/8/5N2/8/1N2K3 w - - 0 1").contains(&"Nbd2".to_string()));
+        assert!(sans("k7/8/8/8/8/4R3/8/4R1K1 w - - 0 1").contains(&"R1e2".to_string()));
+        let queens = sans("8/7k/8/8/8/Q7/8/Q1Q4K w - - 0 1");
+        for san in ["Qa1b2", "Q3b2", "Qcb2"] {
+            assert!(queens.contains(&san.to_string()), "{}", san);
+        }
+
+        let promotions = sans("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1");
+        for san in ["a8=Q", "axb8=N", "axb8=Q+", "axb8=R+"] {
+            assert!(promotions.contains(&san.to_string()), "{}", san);
+        }
+
+        let fools_mate = "rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq - 0 2";
+        assert!(sans(fools_mate).contains(&"Qh4#".to_string()));
+    }
+
+    #[test]
+    fn san_round_trip() {
+        for fen in TEST_FENS {
+            let board = Board::from_fen(fen).unwrap();
+            for m in board.generate_legal_moves() {
+                let rich = board.unpack_move(m).unwrap();
+                assert_eq!(Ok(rich), board.parse_san(&board.to_san(&rich)));
+            }
+        }
+    }
+
+    #[test]
+    fn sloppy_san() {
+        let board = Board::from_fen(TEST_FENS[1]).unwrap();
+        let uci = |san: &str| board.parse_san(san).unwrap().to_uci();
+
+        assert_eq!("e5f7", uci("Nf7"));
+        assert_eq!("e5f7", uci("Nxf7!?"));
+        assert_eq!("e5f7", uci("Ne5f7"));
+        assert_eq!("e1g1", uci("0-0"));
+        assert_eq!("e1c1", uci("O-O-O+"));
+        assert_eq!("g2h3", uci("gh3"));
+        assert_eq!("d5e6", uci("dxe6??"));
+
+        let board = Board::from_fen("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!("a7b8q", board.parse_san("axb8Q").unwrap().to_uci());
+        assert_eq!("a7a8n", board.parse_san("a8=N").unwrap().to_uci());
+        assert_eq!("a7a8r", board.parse_san("a8r").unwrap().to_uci());
+    }
+
+    #[test]
+    fn san_errors() {
+        let board = Board::from_fen("4k3/8/8/8/8/5N2/8/1N2K3 w - - 0 1").unwrap();
+        assert_eq!(
+            Err(SanError::AmbiguousMove("Nd2".to_string())),
+            board.parse_san("Nd2")
+        );
+        for s in ["", "N", "Nz4", "e9", "Pe4", "é4", "Nbb1d2", "O-O-O-O"] {
+            assert_eq!(
+                Err(SanError::InvalidFormat(s.to_string())),
+                board.parse_san(s)
+            );
+        }
+        for s in ["Nb3", "Ncd2", "O-O", "Ke3", "e4"] {
+            assert_eq!(
+                Err(SanError::IllegalMove(s.to_string())),
+                board.parse_san(s)
+            );
+        }
+    }
+}
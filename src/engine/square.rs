@@ -0,0 +1,131 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::engine::bitboard::Bitboard;
+use crate::engine::error::ConversionError;
+use crate::engine::position::{File, Position, Rank};
+
+// index of a square, A1 being 0, B1 1 and H8 63, as in the bitboards
+#[derive(PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Debug, Hash)]
+pub struct Square(u8);
+
+impl Square {
+    pub const fn new(index: usize) -> Self {
+        assert!(index < 64, "square index out of range");
+        Square(index as u8)
+    }
+
+    pub const fn from_coords(rank: Rank, file: File) -> Self {
+        Square(rank as u8 * 8 + file as u8)
+    }
+
+    pub const fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    pub fn rank(self) -> Rank {
+        Rank::ALL[self.index() / 8]
+    }
+
+    pub fn file(self) -> File {
+        File::ALL[self.index() % 8]
+    }
+
+    pub const fn bb(self) -> Bitboard {
+        Bitboard(1 << self.0)
+    }
+
+    // the square `delta` indices away, which must stay on the board
+    pub const fn offset(self, delta: isize) -> Self {
+        Square::new((self.0 as isize + delta) as usize)
+    }
+
+    pub fn iter() -> impl Iterator<Item = Square> {
+        (0..64).map(Square)
+    }
+}
+
+impl From<Position> for Square {
+    fn from(pos: Position) -> Self {
+        Square::from_coords(pos.rank, pos.file)
+    }
+}
+
+impl From<Square> for Position {
+    fn from(sq: Square) -> Self {
+        Position {
+            rank: sq.rank(),
+            file: sq.file(),
+        }
+    }
+}
+
+impl TryFrom<usize> for Square {
+    type Error = ConversionError;
+
+    fn try_from(index: usize) -> Result<Self, Self::Error> {
+        if index < 64 {
+            Ok(Square(index as u8))
+        } else {
+            Err(ConversionError::SquareIndex(index))
+        }
+    }
+}
+
+impl FromStr for Square {
+    type Err = ConversionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.as_bytes() {
+            &[file @ b'a'..=b'h', rank @ b'1'..=b'8'] => {
+                Ok(Square((rank - b'1') * 8 + file - b'a'))
+            }
+            _ => Err(ConversionError::Square(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let file = (b'a' + self.0 % 8) as char;
+        let rank = (b'1' + self.0 / 8) as char;
+        write!(f, "{}{}", file, rank)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coordinates() {
+        let e4 = Square::new(28);
+        assert_eq!(Rank::Four, e4.rank());
+        assert_eq!(File::E, e4.file());
+        assert_eq!(e4, Square::from_coords(Rank::Four, File::E));
+        assert_eq!(Bitboard(0x10000000), e4.bb());
+        assert_eq!(Square::new(36), e4.offset(8));
+
+        let pos = Position::from(e4);
+        assert_eq!(e4, Square::from(pos));
+        assert_eq!(64, Square::iter().count());
+        assert_eq!(Ok(Square::new(63)), Square::try_from(63));
+        assert_eq!(Err(ConversionError::SquareIndex(64)), Square::try_from(64));
+    }
+
+    #[test]
+    fn parse_and_display() {
+        assert_eq!(Ok(Square::new(0)), "a1".parse());
+        assert_eq!(Ok(Square::new(28)), "e4".parse());
+        assert_eq!(Ok(Square::new(63)), "h8".parse());
+        assert_eq!("e4", Square::new(28).to_string());
+        assert_eq!("h1", Square::new(7).to_string());
+
+        for s in ["", "e", "e9", "i4", "E4", "e44", "4e"] {
+            assert_eq!(
+                Err(ConversionError::Square(s.to_string())),
+                s.parse::<Square>()
+            );
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use std::fmt;
+
+use crate::engine::board::Board;
+use crate::engine::piece::PieceType;
+use crate::engine::r#move::Move;
+use crate::engine::square::Square;
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum UciError {
+    // not of the form e2e4 or e7e8q
+    InvalidFormat(String),
+    IllegalMove(String),
+}
+
+impl fmt::Display for UciError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UciError::InvalidFormat(s) => write!(f, "invalid UCI move '{}'", s),
+            UciError::IllegalMove(s) => write!(f, "illegal move '{}'", s),
+        }
+    }
+}
+
+impl std::error::Error for UciError {}
+
+impl Board {
+    // the legal move written `uci` in long algebraic notation
+    pub fn parse_uci_move(&self, uci: &str) -> Result<Move, UciError> {
+        let invalid = || UciError::InvalidFormat(uci.to_string());
+
+        let from: Square = uci
+            .get(0..2)
+            .ok_or_else(invalid)?
+            .parse()
+            .map_err(|_| invalid())?;
+        let to: Square = uci
+            .get(2..4)
+            .ok_or_else(invalid)?
+            .parse()
+            .map_err(|_| invalid())?;
+        let promotion = match &uci[4..] {
+            "" => None,
+            "n" => Some(PieceType::Knight),
+            "b" => Some(PieceType::Bishop),
+            "r" => Some(PieceType::Rook),
+            "q" => Some(PieceType::Queen),
+            _ => return Err(invalid()),
+        };
+
+        self.generate_legal_moves()
+            .into_iter()
+            .find(|m| m.from_square() == from && m.to_square() == to && m.promotion() == promotion)
+            .and_then(|m| self.unpack_move(m))
+            .ok_or_else(|| UciError::IllegalMove(uci.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::board::tests::TEST_FENS;
+
+    #[test]
+    fn parse_uci_moves() {
+        let board = Board::new();
+        let e4 = board.parse_uci_move("e2e4").unwrap();
+        assert_eq!(PieceType::Pawn, e4.piece_type);
+        assert!(!e4.is_capture);
+        assert_eq!("e2e4", e4.to_uci());
+        assert_eq!(
+            PieceType::Knight,
+            board.parse_uci_move("g1f3").unwrap().piece_type
+        );
+
+        let board = Board::from_fen(TEST_FENS[1]).unwrap();
+        let castle = board.parse_uci_move("e1g1").unwrap();
+        assert_eq!(PieceType::King, castle.piece_type);
+        let capture = board.parse_uci_move("e5f7").unwrap();
+        assert!(capture.is_capture);
+
+        let board = Board::from_fen("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let promotion = board.parse_uci_move("b7b8n").unwrap();
+        assert_eq!(Some(PieceType::Knight), promotion.promotion);
+        assert_eq!("b7b8n", promotion.to_uci());
+    }
+
+    #[test]
+    fn uci_round_trip() {
+        for fen in TEST_FENS {
+            let board = Board::from_fen(fen).unwrap();
+            for m in board.generate_legal_moves() {
+                let rich = board.unpack_move(m).unwrap();
+                assert_eq!(m.to_string(), rich.to_uci());
+                assert_eq!(Ok(rich), board.parse_uci_move(&rich.to_uci()));
+            }
+        }
+    }
+
+    #[test]
+    fn uci_errors() {
+        let board = Board::new();
+        for s in ["", "e2", "e2e", "e2e9", "e2e4x", "e2e4Q", "e2-e4", "é2e4"] {
+            assert_eq!(
+                Err(UciError::InvalidFormat(s.to_string())),
+                board.parse_uci_move(s)
+            );
+        }
+        for s in ["e2e5", "e1g1", "e7e5", "e2e4q", "a1a1"] {
+            assert_eq!(
+                Err(UciError::IllegalMove(s.to_string())),
+                board.parse_uci_move(s)
+            );
+        }
+    }
+}
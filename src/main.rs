@@ -1,6 +1,92 @@
+use std::env;
+use std::process::ExitCode;
+
 use chess::engine::board::Board;
 
-fn main() {
-    let b = Board::new();
-    println!("{:?}", b);
+const USAGE: &str = "usage: chess [perft <depth> [--fen <fen>] [--divide]]";
+
+struct PerftArgs {
+    depth: u32,
+    fen: Option<String>,
+    divide: bool,
+}
+
+// the FEN may be given as one quoted argument or as its separate fields
+fn parse_perft_args(args: &[String]) -> Result<PerftArgs, String> {
+    let mut depth = None;
+    let mut fen = None;
+    let mut divide = false;
+
+    let mut args = args.iter().peekable();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--divide" => divide = true,
+            "--fen" => {
+                // a quoted FEN or its six fields as separate arguments
+                let mut fields = Vec::new();
+                while fields.len() < 6 {
+                    let Some(arg) = args.next_if(|a| !a.starts_with("--")) else {
+                        break;
+                    };
+                    fields.extend(arg.split_whitespace());
+                }
+                if fields.is_empty() {
+                    return Err("--fen expects a position".to_string());
+                }
+                fen = Some(fields.join(" "));
+            }
+            s if depth.is_none() => {
+                depth = Some(s.parse().map_err(|_| format!("invalid depth '{}'", s))?);
+            }
+            s => return Err(format!("unexpected argument '{}'", s)),
+        }
+    }
+
+    Ok(PerftArgs {
+        depth: depth.ok_or("missing depth")?,
+        fen,
+        divide,
+    })
+}
+
+fn perft(args: &[String]) -> Result<(), String> {
+    let args = parse_perft_args(args)?;
+    let mut board = match &args.fen {
+        Some(fen) => Board::from_fen(fen).map_err(|e| e.to_string())?,
+        None => Board::new(),
+    };
+
+    if args.divide {
+        let divide = board.perft_divide(args.depth);
+        for (m, nodes) in &divide {
+            println!("{}: {}", m, nodes);
+        }
+        let total: u64 = divide.iter().map(|(_, nodes)| nodes).sum();
+        println!("\nNodes searched: {}", total);
+    } else {
+        println!("{}", board.perft(args.depth));
+    }
+
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let result = match args.first().map(String::as_str) {
+        None => {
+            println!("{:?}", Board::new());
+            Ok(())
+        }
+        Some("perft") => perft(&args[1..]),
+        Some(command) => Err(format!("unknown command '{}'", command)),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}\n{}", e, USAGE);
+            ExitCode::from(2)
+        }
+    }
 }
@@ -1,28 +1,126 @@
 use chess::engine;
 
-fn perft(board: engine::board::Board, depth: u32) -> usize {
-    let moves = board.generate_legal_moves();
+#[test]
+fn initial_pos() {
+    let mut b = engine::board::Board::new();
+    assert_eq!(b.perft(1), 20);
+    assert_eq!(b.perft(2), 400);
+    assert_eq!(b.perft(3), 8902);
+    assert_eq!(b.perft(4), 197281);
+}
+
+#[test]
+fn kiwipete() {
+    let mut b = engine::board::Board::from_fen(
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    )
+    .unwrap();
+    assert_eq!(b.perft(1), 48);
+    assert_eq!(b.perft(2), 2039);
+    assert_eq!(b.perft(3), 97862);
+}
+
+// published breakdowns, see https://www.chessprogramming.org/Perft_Results
+fn stats(fen: &str, depth: u32) -> [u64; 9] {
+    let s = engine::board::Board::from_fen(fen)
+        .unwrap()
+        .perft_stats(depth);
+    [
+        s.nodes,
+        s.captures,
+        s.en_passants,
+        s.castles,
+        s.promotions,
+        s.checks,
+        s.discovered_checks,
+        s.double_checks,
+        s.checkmates,
+    ]
+}
+
+const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+const POSITION_3: &str = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+
+#[test]
+fn initial_pos_stats() {
+    let fen = engine::fen::STARTING_FEN;
+    assert_eq!(stats(fen, 1), [20, 0, 0, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(stats(fen, 2), [400, 0, 0, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(stats(fen, 3), [8902, 34, 0, 0, 0, 12, 0, 0, 0]);
+    assert_eq!(stats(fen, 4), [197281, 1576, 0, 0, 0, 469, 0, 0, 8]);
+}
+
+#[test]
+#[ignore]
+fn initial_pos_stats_deep() {
+    let fen = engine::fen::STARTING_FEN;
+    assert_eq!(stats(fen, 5), [4865609, 82719, 258, 0, 0, 27351, 6, 0, 347]);
+}
+
+#[test]
+fn kiwipete_stats() {
+    assert_eq!(stats(KIWIPETE, 1), [48, 8, 0, 2, 0, 0, 0, 0, 0]);
+    assert_eq!(stats(KIWIPETE, 2), [2039, 351, 1, 91, 0, 3, 0, 0, 0]);
+    assert_eq!(
+        stats(KIWIPETE, 3),
+        [97862, 17102, 45, 3162, 0, 993, 0, 0, 1]
+    );
+}
+
+#[test]
+#[ignore]
+fn kiwipete_stats_deep() {
+    assert_eq!(
+        stats(KIWIPETE, 4),
+        [4085603, 757163, 1929, 128013, 15172, 25523, 42, 6, 43]
+    );
+}
+
+#[test]
+#[ignore]
+fn position_3_stats_deep() {
+    assert_eq!(
+        stats(POSITION_3, 5),
+        [674624, 52051, 1165, 0, 0, 52950, 1292, 3, 0]
+    );
+}
 
-    if depth == 1 {
-        return moves.len();
+// positions with known perft counts, one per line: "<fen> ;D1 20 ;D2 400 ..."
+const SUITE: &str = include_str!("data/perft.epd");
+
+// counts above this are left to the ignored test
+const FAST_NODES: u64 = 1_000_000;
+
+fn suite() -> Vec<(&'static str, u32, u64)> {
+    let mut cases = Vec::new();
+
+    for line in SUITE.lines().filter(|l| !l.trim().is_empty()) {
+        let mut fields = line.split(';');
+        let fen = fields.next().unwrap().trim();
+        for field in fields {
+            let (depth, nodes) = field.trim().split_once(' ').unwrap();
+            let depth = depth.strip_prefix('D').unwrap().parse().unwrap();
+            cases.push((fen, depth, nodes.parse().unwrap()));
+        }
     }
 
-    let mut nodes = 0;
-    for m in moves {
-        let mut bis = board.clone();
-        bis.do_move(&m);
-        nodes += perft(bis, depth - 1);
+    cases
+}
+
+fn check_suite(run: impl Fn(u64) -> bool) {
+    for (fen, depth, nodes) in suite().into_iter().filter(|(_, _, n)| run(*n)) {
+        let mut board = engine::board::Board::from_fen(fen).unwrap();
+        assert_eq!(nodes, board.perft(depth), "{} depth {}", fen, depth);
     }
+}
 
-    nodes
+#[test]
+fn perft_suite() {
+    check_suite(|nodes| nodes <= FAST_NODES);
 }
 
-#[ignore = "Move generation with blockers not yet implemented"]
 #[test]
-fn initial_pos() {
-    let b = engine::board::Board::new();
-    assert_eq!(perft(b.clone(), 1), 20);
-    assert_eq!(perft(b.clone(), 2), 400);
-    assert_eq!(perft(b.clone(), 3), 8902);
-    assert_eq!(perft(b.clone(), 4), 197281);
+#[ignore]
+fn perft_suite_deep() {
+    check_suite(|nodes| nodes > FAST_NODES);
 }